@@ -0,0 +1,426 @@
+use std::path::PathBuf;
+use argh::FromArgs;
+
+use crate::types::{DuplicatePolicy, ExportFormat, NodeStatus, ScoreStrategy};
+
+#[derive(FromArgs, Debug)]
+/// Minimalist command-line habit tracker.
+pub struct Cli {
+    #[argh(option)]
+    /// directory holding userdata.bin, overrides $HABIT_TRACKER_DATA and $XDG_DATA_HOME/habit-tracker
+    pub data_dir: Option<PathBuf>,
+    #[argh(subcommand)]
+    pub command: Command,
+}
+
+#[derive(FromArgs, Debug)]
+#[argh(subcommand)]
+pub enum Command {
+    Complete(CompleteCmd),
+    Fail(FailCmd),
+    Skip(SkipCmd),
+    Reset(ResetCmd),
+    Increment(IncrementCmd),
+    Set(SetCmd),
+    AddHabit(AddHabitCmd),
+    RemoveHabit(RemoveHabitCmd),
+    HideHabit(HideHabitCmd),
+    EditHabit(EditHabitCmd),
+    List(ListCmd),
+    History(HistoryCmd),
+    Calendar(CalendarCmd),
+    Week(WeekCmd),
+    ResetAll(ResetAllCmd),
+    Where(WhereCmd),
+    Doctor(DoctorCmd),
+    Export(ExportCmd),
+    Import(ImportCmd),
+    Undo(UndoCmd),
+    Redo(RedoCmd),
+    Log(LogCmd),
+}
+
+#[derive(FromArgs, Debug)]
+#[argh(subcommand, name = "complete")]
+/// Mark a habit as complete, defaults to today.
+pub struct CompleteCmd {
+    #[argh(positional)]
+    /// name of the habit
+    pub habit: String,
+    #[argh(option)]
+    /// date to edit (M-D-YYYY, YYYY-MM-DD, today, yesterday, -N or a weekday like mon), defaults to today
+    pub date: Option<String>,
+    #[argh(switch)]
+    /// record even if the habit isn't scheduled on that day (counts as a bonus day)
+    pub force: bool,
+}
+
+#[derive(FromArgs, Debug)]
+#[argh(subcommand, name = "fail")]
+/// Mark a habit as failed, defaults to today.
+pub struct FailCmd {
+    #[argh(positional)]
+    /// name of the habit
+    pub habit: String,
+    #[argh(option)]
+    /// date to edit (M-D-YYYY, YYYY-MM-DD, today, yesterday, -N or a weekday like mon), defaults to today
+    pub date: Option<String>,
+    #[argh(switch)]
+    /// record even if the habit isn't scheduled on that day (counts as a bonus day)
+    pub force: bool,
+}
+
+#[derive(FromArgs, Debug)]
+#[argh(subcommand, name = "skip")]
+/// Mark a habit as skipped, defaults to today.
+pub struct SkipCmd {
+    #[argh(positional)]
+    /// name of the habit
+    pub habit: String,
+    #[argh(option)]
+    /// date to edit (M-D-YYYY, YYYY-MM-DD, today, yesterday, -N or a weekday like mon), defaults to today
+    pub date: Option<String>,
+    #[argh(switch)]
+    /// record even if the habit isn't scheduled on that day (counts as a bonus day)
+    pub force: bool,
+}
+
+#[derive(FromArgs, Debug)]
+#[argh(subcommand, name = "reset")]
+/// Reset a habit node, defaults to today.
+pub struct ResetCmd {
+    #[argh(positional)]
+    /// name of the habit
+    pub habit: String,
+    #[argh(option)]
+    /// date to edit (M-D-YYYY, YYYY-MM-DD, today, yesterday, -N or a weekday like mon), defaults to today
+    pub date: Option<String>,
+}
+
+#[derive(FromArgs, Debug)]
+#[argh(subcommand, name = "increment")]
+/// Add a value to a habit with a numerical goal, defaults to today.
+pub struct IncrementCmd {
+    #[argh(positional)]
+    /// name of the habit
+    pub habit: String,
+    #[argh(positional)]
+    /// amount to add, in the habit's unit or another of the same kind, ex: 20, 1h30m, 2.5km
+    pub value: String,
+    #[argh(option)]
+    /// date to edit (M-D-YYYY, YYYY-MM-DD, today, yesterday, -N or a weekday like mon), defaults to today
+    pub date: Option<String>,
+    #[argh(switch)]
+    /// record even if the habit isn't scheduled on that day (counts as a bonus day)
+    pub force: bool,
+}
+
+#[derive(FromArgs, Debug)]
+#[argh(subcommand, name = "set")]
+/// Overwrite the existing value for a habit, defaults to today.
+pub struct SetCmd {
+    #[argh(positional)]
+    /// name of the habit
+    pub habit: String,
+    #[argh(positional)]
+    /// new value, in the habit's unit or another of the same kind, ex: 20, 1h30m, 2.5km
+    pub value: String,
+    #[argh(option)]
+    /// date to edit (M-D-YYYY, YYYY-MM-DD, today, yesterday, -N or a weekday like mon), defaults to today
+    pub date: Option<String>,
+    #[argh(switch)]
+    /// record even if the habit isn't scheduled on that day (counts as a bonus day)
+    pub force: bool,
+}
+
+#[derive(FromArgs, Debug)]
+#[argh(subcommand, name = "add_habit")]
+/// Add a new habit to track.
+pub struct AddHabitCmd {
+    #[argh(positional)]
+    /// name of the new habit
+    pub name: String,
+    #[argh(option)]
+    /// daily goal, ex: 5000 for a habit of walking 5000 steps, or 1h30m for a habit measured in minutes
+    pub goal: String,
+    #[argh(option)]
+    /// what the goal and values are measured in: min, h, m, km, mi, ml, l or any label like steps, defaults to a plain count
+    pub unit: Option<String>,
+    #[argh(option)]
+    /// which side of the goal counts as done: at-least (default), at-most (the goal is a limit), exact or range
+    pub direction: Option<String>,
+    #[argh(option)]
+    /// lowest amount that counts for a range, the goal being the highest, ex: --direction range --min 7 --goal 9
+    pub min: Option<String>,
+    #[argh(option, default = "String::new()")]
+    /// short description of the habit
+    pub desc: String,
+    #[argh(option)]
+    /// enabled days as ISO weekday numbers (1 = Monday ... 7 = Sunday), ex: 1-3-5, defaults to every day
+    pub days: Option<String>,
+    #[argh(option)]
+    /// how often: daily (default), weekly, monthly, every-other-day, or N/week, N/month, N/Kdays (K up to 365), ex: 3/week
+    pub frequency: Option<String>,
+    #[argh(option, default = "NodeStatus::IDLE", from_str_fn(parse_missed_status))]
+    /// status given to scheduled days that were never logged: idle (default) or failed; at-most habits always use idle
+    pub missed: NodeStatus,
+}
+
+#[derive(FromArgs, Debug)]
+#[argh(subcommand, name = "remove_habit")]
+/// Delete a habit and all of that habit's history.
+pub struct RemoveHabitCmd {
+    #[argh(positional)]
+    /// name of the habit
+    pub name: String,
+}
+
+#[derive(FromArgs, Debug)]
+#[argh(subcommand, name = "hide_habit")]
+/// Stop showing a habit, but keep its history saved.
+pub struct HideHabitCmd {
+    #[argh(positional)]
+    /// name of the habit
+    pub name: String,
+}
+
+#[derive(FromArgs, Debug)]
+#[argh(subcommand, name = "edit_habit")]
+/// Rename a habit or change its description, days, frequency, goal or unit, keeping its history.
+pub struct EditHabitCmd {
+    #[argh(positional)]
+    /// name of the habit
+    pub name: String,
+    #[argh(option)]
+    /// new name for the habit
+    pub rename: Option<String>,
+    #[argh(option)]
+    /// new description
+    pub desc: Option<String>,
+    #[argh(option)]
+    /// new enabled days as ISO weekday numbers (1 = Monday ... 7 = Sunday), ex: 1-3-5, or "all" for every day; days before --from keep the old ones
+    pub days: Option<String>,
+    #[argh(option)]
+    /// new frequency: daily, weekly, monthly, every-other-day, or N/week, N/month, N/Kdays; days before --from keep the old one
+    pub frequency: Option<String>,
+    #[argh(option)]
+    /// new daily goal, days already logged keep the goal they were logged against
+    pub goal: Option<String>,
+    #[argh(option)]
+    /// first day the new goal, days or frequency apply to (M-D-YYYY, YYYY-MM-DD, today, yesterday, -N or a weekday like mon), defaults to today
+    pub from: Option<String>,
+    #[argh(switch)]
+    /// also give days already logged from that day on the new goal, and work out their status again
+    pub recalculate: bool,
+    #[argh(option)]
+    /// new unit (min, h, m, km, mi, ml, l or any label like steps, "none" for a plain count); values are converted between units of the same kind
+    pub unit: Option<String>,
+    #[argh(option)]
+    /// new direction: at-least, at-most, exact or range; logged days are judged again
+    pub direction: Option<String>,
+    #[argh(option)]
+    /// new lowest amount for a range
+    pub min: Option<String>,
+    #[argh(switch)]
+    /// show what would change without changing anything
+    pub dry_run: bool,
+}
+
+#[derive(FromArgs, Debug)]
+#[argh(subcommand, name = "list")]
+/// Show a status list of all active habits scheduled for a day, defaults to today.
+pub struct ListCmd {
+    #[argh(option)]
+    /// date to list (M-D-YYYY, YYYY-MM-DD, today, yesterday, -N or a weekday like mon), defaults to today
+    pub date: Option<String>,
+    #[argh(switch)]
+    /// sort by name, ignoring the order set in config.toml
+    pub by_name: bool,
+}
+
+#[derive(FromArgs, Debug)]
+#[argh(subcommand, name = "history")]
+/// Show to-date data of a habit, tracking % of completed days.
+pub struct HistoryCmd {
+    #[argh(positional)]
+    /// name of the habit
+    pub habit: String,
+    #[argh(option, from_str_fn(parse_score_strategy))]
+    /// only show one score: strict, proportional or strength, defaults to all three
+    pub score: Option<ScoreStrategy>,
+}
+
+#[derive(FromArgs, Debug)]
+#[argh(subcommand, name = "calendar")]
+/// Draw a heatmap of a habit's history, one column per week.
+pub struct CalendarCmd {
+    #[argh(positional)]
+    /// name of the habit
+    pub habit: String,
+    #[argh(option)]
+    /// number of weeks to show, ending this week, defaults to 26, at most 520
+    pub weeks: Option<u32>,
+    #[argh(option)]
+    /// show a whole calendar year instead, ex: 2023
+    pub year: Option<i32>,
+}
+
+#[derive(FromArgs, Debug)]
+#[argh(subcommand, name = "week")]
+/// Show every active habit against the seven days of a week, defaults to this week.
+pub struct WeekCmd {
+    #[argh(option)]
+    /// any day of the week to show (M-D-YYYY, YYYY-MM-DD, today, yesterday, -N or a weekday like mon), defaults to today
+    pub date: Option<String>,
+}
+
+#[derive(FromArgs, Debug)]
+#[argh(subcommand, name = "reset_all")]
+/// Delete every habit and all history.
+pub struct ResetAllCmd {}
+
+#[derive(FromArgs, Debug)]
+#[argh(subcommand, name = "where")]
+/// Print the path of the data file in use.
+pub struct WhereCmd {}
+
+#[derive(FromArgs, Debug)]
+#[argh(subcommand, name = "export")]
+/// Write habits and their history as JSON, TOML or CSV.
+pub struct ExportCmd {
+    #[argh(option, default = "ExportFormat::Json", from_str_fn(parse_export_format))]
+    /// output format: json (default), toml or csv
+    pub format: ExportFormat,
+    #[argh(option, short = 'o')]
+    /// file to write to, defaults to stdout
+    pub output: Option<PathBuf>,
+    #[argh(option)]
+    /// first day to include (same date formats as --date on other commands)
+    pub from: Option<String>,
+    #[argh(option)]
+    /// last day to include (same date formats as --date on other commands)
+    pub to: Option<String>,
+    #[argh(option)]
+    /// only export this habit, can be repeated
+    pub habit: Vec<String>,
+    #[argh(switch)]
+    /// csv only: one row per day with a column per habit
+    pub wide: bool,
+}
+
+#[derive(FromArgs, Debug)]
+#[argh(subcommand, name = "import")]
+/// Load habits and history from an export, a Loop Habit Tracker backup or a CSV file.
+pub struct ImportCmd {
+    #[argh(positional)]
+    /// file to import, or the unzipped backup folder with --from loop
+    pub file: PathBuf,
+    #[argh(option, default = "ImportSource::Export", from_str_fn(parse_import_source))]
+    /// where the data comes from: export (default, a file written by export), loop or csv
+    pub from: ImportSource,
+    #[argh(option, from_str_fn(parse_export_format))]
+    /// export only: json or toml, defaults to the file extension
+    pub format: Option<ExportFormat>,
+    #[argh(switch)]
+    /// replace all existing habits instead of merging into them
+    pub replace: bool,
+    #[argh(option, default = "DuplicatePolicy::Overwrite", from_str_fn(parse_duplicate_policy))]
+    /// what to do with days logged both here and in the file: overwrite (default), keep, max or error
+    pub on_duplicate: DuplicatePolicy,
+    #[argh(option)]
+    /// csv only: columns to read, ex: date=Day,habit=Name,value=Steps,status=Result
+    pub map: Option<String>,
+    #[argh(option)]
+    /// csv only: habit name to use when the file has no habit column
+    pub habit: Option<String>,
+    #[argh(option, default = "1.0")]
+    /// csv only: daily goal of the imported days, defaults to 1
+    pub goal: f64,
+    #[argh(option)]
+    /// csv only: how dates are written, ex: %d/%m/%Y, defaults to the usual date formats
+    pub date_format: Option<String>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ImportSource {
+    Export,
+    Loop,
+    Csv,
+}
+
+fn parse_import_source(value: &str) -> Result<ImportSource, String> {
+    match value.to_lowercase().as_str() {
+        "export" => Ok(ImportSource::Export),
+        "loop" => Ok(ImportSource::Loop),
+        "csv" => Ok(ImportSource::Csv),
+        _ => Err("expected export, loop or csv".to_string()),
+    }
+}
+
+fn parse_duplicate_policy(value: &str) -> Result<DuplicatePolicy, String> {
+    match value.to_lowercase().as_str() {
+        "keep" => Ok(DuplicatePolicy::Keep),
+        "overwrite" => Ok(DuplicatePolicy::Overwrite),
+        "max" => Ok(DuplicatePolicy::Max),
+        "error" => Ok(DuplicatePolicy::Error),
+        _ => Err("expected overwrite, keep, max or error".to_string()),
+    }
+}
+
+#[derive(FromArgs, Debug)]
+#[argh(subcommand, name = "doctor")]
+/// Check the saved data for inconsistencies and repair them.
+pub struct DoctorCmd {
+    #[argh(switch)]
+    /// only report problems, exiting with code 9 if there are any
+    pub check: bool,
+}
+
+#[derive(FromArgs, Debug)]
+#[argh(subcommand, name = "undo")]
+/// Undo the last command that changed anything, or the last N.
+pub struct UndoCmd {
+    #[argh(positional, default = "1")]
+    /// number of commands to undo, defaults to 1
+    pub count: usize,
+}
+
+#[derive(FromArgs, Debug)]
+#[argh(subcommand, name = "redo")]
+/// Redo the last undone command.
+pub struct RedoCmd {}
+
+#[derive(FromArgs, Debug)]
+#[argh(subcommand, name = "log")]
+/// List the commands that can be undone, newest first.
+pub struct LogCmd {
+    #[argh(option, default = "20")]
+    /// number of commands to show, defaults to 20
+    pub limit: usize,
+}
+
+fn parse_export_format(value: &str) -> Result<ExportFormat, String> {
+    match value.to_lowercase().as_str() {
+        "json" => Ok(ExportFormat::Json),
+        "toml" => Ok(ExportFormat::Toml),
+        "csv" => Ok(ExportFormat::Csv),
+        _ => Err("expected json, toml or csv".to_string()),
+    }
+}
+
+fn parse_score_strategy(value: &str) -> Result<ScoreStrategy, String> {
+    match value.to_lowercase().as_str() {
+        "strict" => Ok(ScoreStrategy::Strict),
+        "proportional" => Ok(ScoreStrategy::Proportional),
+        "strength" => Ok(ScoreStrategy::Strength),
+        _ => Err("expected strict, proportional or strength".to_string()),
+    }
+}
+
+fn parse_missed_status(value: &str) -> Result<NodeStatus, String> {
+    match value.to_lowercase().as_str() {
+        "idle" => Ok(NodeStatus::IDLE),
+        "failed" | "fail" => Ok(NodeStatus::FAILED),
+        _ => Err("expected idle or failed".to_string()),
+    }
+}
@@ -1,186 +1,258 @@
+mod cli;
+mod storage;
 mod types;
 
-use std::io::{Read, Write};
-use std::env;
-use std::fs::File;
+use std::fs;
+use std::io::{self, Write};
+use std::path::Path;
+use std::process;
 
+use cli::*;
 use types::*;
 
 fn main() {
-    let args: Vec<String> = env::args().collect();
-    dbg!(args.clone());
+    let cli: Cli = argh::from_env();
+    let command_line = command_line(std::env::args().skip(1));
 
-    if args.clone().len() < 2 {
-        panic!("Habit Tracker -- Error: No command specified!");
-    }
-
-    let user_data_result: Result<UserData, String> = match File::open("userdata.bin") {
-        Ok(file) => {
-            let mut data = Vec::new();
-            let _ = file.take(u64::MAX).read_to_end(&mut data);
+    let dir = match storage::data_dir(cli.data_dir) {
+        Ok(dir) => dir,
+        Err(e) => fail(e),
+    };
+    let config = match storage::load_config(&dir) {
+        Ok(config) => config,
+        Err(e) => fail(e),
+    };
+    let store = match storage::open(&dir, &config) {
+        Ok(store) => store,
+        Err(e) => fail(e),
+    };
+    let journal_file = storage::JournalFile::new(dir.join(storage::JOURNAL_FILE));
 
-            match bincode::deserialize(&data) {
-                Ok(decoded) => {
-                    Ok(decoded)
-                },
-                Err(_) => {
-                    Err("Unable to deserialize usedata.bin".to_string())
-                },
+    // Read-only commands that shouldn't rewrite the data file
+    let command = match cli.command {
+        Command::Where(_) => {
+            print(&store.location());
+            return;
+        },
+        Command::Export(cmd) => {
+            match export(store.as_ref(), cmd) {
+                Ok(msg) => print(&msg),
+                Err(e) => fail(e),
             }
+            return;
         },
-        Err(e) => { 
-            Err(e.to_string())
-        }
+        Command::Log(cmd) => {
+            match journal_file.load() {
+                Ok(journal) => print(&journal.log(cmd.limit)),
+                Err(e) => fail(e),
+            }
+            return;
+        },
+        command => command,
     };
 
-    let mut user_data = match user_data_result {
-        Ok(data) => {
-            data
-        },
-        Err(e) => {
-            println!("{}", e);
-            UserData::new()
-        },
+    let path = dir.join(storage::DATA_FILE);
+    if store.location() == path.display().to_string() && !path.exists() && Path::new(storage::DATA_FILE).exists() {
+        eprintln!("Note: found {} in the current directory, but data is now kept at {}. Move it there to keep your history.",
+            storage::DATA_FILE, path.display());
+    }
+
+    let mut journal = match journal_file.load() {
+        Ok(journal) => journal,
+        Err(e) => fail(e),
     };
-    println!("{:?}", &user_data);
-    
-    let command = args[1].clone();
-    let arg2 = args.get(2).map(|s| s.to_string());
-    let arg3 = args.get(3).map(|s| s.to_string());
-    let arg4 = args.get(4).map(|s| s.to_string());
-    let arg5 = args.get(5).map(|s| s.to_string());
-
-    match command.clone().as_str() {
-        "help" => {
-            println!("Habit Tracker Commands: ");
-            println!("skip <habit> <opt date> (mark a habit as skipped, defaults to today");
-            println!("complete <habit> <opt date> (mark a habit as complete, defaults to today");
-            println!("fail <habit> <opt date> (mark a habit as failed, defaults to today");
-            println!("increment <habit> <value> <opt date> (add value to a habit with a numerical goal, defaults to today)");
-            println!("set <habit> <value> <opt date> (overwrites existing value for a habit, defaults to today)");
-            println!("reset <habit> <opt date> (reset a habit node, defaults to today)");
-            println!("add_habit <habit name> <desc> <goal> <opt enabled days as 1-3-5-7 etc> (adds a new habit to track)");
-            println!("remove_habit <habit name> (deletes a habit and all of that habit's history)");
-            println!("hide_habit <habit name> (stops showing a habit, but keeps the history saved and will not mark days as skipped)");
-            println!("list <opt date> (shows a status list of all active habits at the specified date, defaults to today)");
-            println!("history <habit> (shows to-date data of the specified habit, tracking % of completed days)");
-            return
-        },
-        "reset_all" => {
-            // TODO: Expand this with an extra step to prevent accidental deletion
+
+    // Every command that changes anything is journaled, so it can be undone later
+    let result = store.transaction(HabitID::today(), Box::new(|user_data| {
+        match command {
+            Command::Undo(cmd) => journal.undo(user_data, cmd.count),
+            Command::Redo(_) => journal.redo(user_data),
+            command => {
+                let before = user_data.clone();
+                let msg = run(user_data, command, &config)?;
+                journal.record(&command_line, &before, user_data);
+                Ok(msg)
+            },
+        }
+    }));
+
+    match result {
+        Ok(msg) => {
+            if let Err(e) = journal_file.save(&mut journal) {
+                eprintln!("Warning: the change was saved, but the undo journal couldn't be written: {}", e);
+            }
+            if !msg.is_empty() {
+                print(&msg);
+            }
+        },
+        Err(e) => fail(e),
+    }
+}
+
+// Writes output for the user. A reader that stops early, ex: `history walk | head -3`, isn't an error.
+fn print(text: &str) {
+    let mut stdout = io::stdout().lock();
+    match writeln!(stdout, "{}", text).and_then(|_| stdout.flush()) {
+        Ok(()) => {},
+        Err(e) if e.kind() == io::ErrorKind::BrokenPipe => process::exit(0),
+        Err(e) => fail(e.into()),
+    }
+}
+
+fn fail(e: HabitError) -> ! {
+    eprintln!("Error: {}", e);
+    process::exit(e.exit_code());
+}
+
+fn run(user_data: &mut UserData, command: Command, config: &storage::Config) -> Result<String, HabitError> {
+    match command {
+        Command::ResetAll(_) => {
             user_data.clear_data();
+            Ok("Deleted every habit, `undo` brings them back".to_string())
         },
-        "add_habit" => {
-            if let Some(habit_name) = arg2 {
-                match (arg3, arg4, arg5) {
-                    (Some(desc), Some(goal), Some(days)) => {
-                        let new_data = HabitData::new(desc, goal.parse::<i32>().unwrap(), Some(days));
-                        let result = user_data.add_habit(habit_name, new_data);
-                        println!("{:?}", result);
-                    },
-                    (Some(desc), Some(goal), None) => {
-                        let new_data = HabitData::new(desc, goal.parse::<i32>().unwrap(), None);
-                        let result = user_data.add_habit(habit_name, new_data);
-                        println!("{:?}", result);
-                    }
-                    _ => {
-                        println!("Error: called add_habit without all arguments accounted for");
-                    }
-                }
+        Command::AddHabit(cmd) => {
+            let unit = Unit::from_name(cmd.unit.as_deref().unwrap_or_default());
+            let min = cmd.min.map(|min| unit.parse_amount(&min)).transpose()?;
+            let direction = GoalDirection::from_name(cmd.direction.as_deref().unwrap_or("at-least"), min)?;
+            let mut new_data = HabitData::new(cmd.desc, unit.parse_amount(&cmd.goal)?, cmd.days)?;
+            new_data.set_missed_status(cmd.missed);
+            new_data.set_unit(unit);
+            new_data.set_direction(direction)?;
+            if let Some(frequency) = cmd.frequency {
+                new_data.set_frequency(Frequency::parse(&frequency)?)?;
             }
+            user_data.add_habit(cmd.name, new_data)
         },
-        "remove_habit" => {
-            if let Some(habit_name) = arg2 {
-                let result = user_data.remove_habit(habit_name);
-                println!("{:?}", result);
-            }
+        Command::RemoveHabit(cmd) => {
+            user_data.remove_habit(cmd.name)
         },
-        "hide_habit" => {
-            if let Some(habit_name) = arg2 {
-                let result = user_data.hide_habit(habit_name);
-                println!("{:?}", result);
-            }
+        Command::HideHabit(cmd) => {
+            user_data.hide_habit(cmd.name)
         },
-        "complete" | "fail" | "skip" | "reset" => {
-            match arg3 {
-                Some(date) => {
-                    let result = user_data.edit_habit_node(args.clone(), date, 0);
-                    println!("{:?}", result);
-                },
-                None => {
-                    let today = HabitData::get_current_date_id();
-                    let result = user_data.edit_habit_node(args.clone(), today, 0);
-                    println!("{:?}", result);
-                },
+        Command::EditHabit(cmd) => {
+            let edit = HabitEdit {
+                rename: cmd.rename.clone(),
+                description: cmd.desc,
+                days: cmd.days,
+                frequency: cmd.frequency,
+                goal: cmd.goal,
+                from: cmd.from.map(|d| HabitID::parse(&d)).transpose()?,
+                recalculate: cmd.recalculate,
+                unit: cmd.unit,
+                direction: cmd.direction,
+                min: cmd.min,
+            };
+            let mut message = user_data.edit_habit(cmd.name.clone(), &edit, cmd.dry_run)?;
+            // config.toml is only ever read, so point out an order entry left behind by a rename
+            if cmd.rename.is_some() && config.list.order.contains(&cmd.name) {
+                message.push_str(&format!("\nconfig.toml still lists '{}' in [list] order", cmd.name));
             }
+            Ok(message)
+        },
+        Command::Complete(cmd) => {
+            user_data.edit_habit_node(cmd.habit, "complete", date_or_today(cmd.date)?, 0.0, cmd.force)
+        },
+        Command::Fail(cmd) => {
+            user_data.edit_habit_node(cmd.habit, "fail", date_or_today(cmd.date)?, 0.0, cmd.force)
+        },
+        Command::Skip(cmd) => {
+            user_data.edit_habit_node(cmd.habit, "skip", date_or_today(cmd.date)?, 0.0, cmd.force)
+        },
+        Command::Reset(cmd) => {
+            user_data.edit_habit_node(cmd.habit, "reset", date_or_today(cmd.date)?, 0.0, false)
+        },
+        Command::Increment(cmd) => {
+            let value = user_data.parse_amount(&cmd.habit, &cmd.value)?;
+            user_data.edit_habit_node(cmd.habit, "increment", date_or_today(cmd.date)?, value, cmd.force)
         },
-        "increment" | "set" => {
-            let mut value = 0;
-            match arg3 {
-                Some(v) => {
-                    match v.parse::<i32>() {
-                        Ok(val) => {
-                            value = val;
-                        },
-                        Err(e) => {
-                            println!("{:?}", e.to_string());
-                        },
-                    }
+        Command::Set(cmd) => {
+            let value = user_data.parse_amount(&cmd.habit, &cmd.value)?;
+            user_data.edit_habit_node(cmd.habit, "set", date_or_today(cmd.date)?, value, cmd.force)
+        },
+        Command::History(cmd) => {
+            user_data.show_history(cmd.habit, cmd.score)
+        },
+        Command::Calendar(cmd) => {
+            let (from, to) = calendar_range(cmd.weeks, cmd.year, HabitID::today())?;
+            user_data.show_calendar(cmd.habit, from, to, &Palette::for_stdout())
+        },
+        Command::Week(cmd) => {
+            user_data.week_grid(date_or_today(cmd.date)?, HabitID::today(), &config.list.order, &Palette::for_stdout())
+        },
+        Command::List(cmd) => {
+            let order = if cmd.by_name { &[] } else { config.list.order.as_slice() };
+            user_data.habit_list_for_day(date_or_today(cmd.date)?, order, &Palette::for_stdout())
+        },
+        Command::Doctor(cmd) => {
+            user_data.doctor(cmd.check)
+        },
+        Command::Import(cmd) => {
+            let export = match cmd.from {
+                ImportSource::Export => {
+                    let format = match cmd.format {
+                        Some(format) => format,
+                        None => format_from_extension(&cmd.file)?,
+                    };
+                    UserData::parse_export(&fs::read_to_string(&cmd.file)?, format)?
                 },
-                None => {
-                    println!("Error: No value given for the increment command.");
+                ImportSource::Loop => export_from_loop(&cmd.file)?,
+                ImportSource::Csv => {
+                    let mut mapping = CsvMapping { habit_name: cmd.habit, goal: cmd.goal, date_format: cmd.date_format, ..Default::default() };
+                    mapping.parse_map(cmd.map.as_deref().unwrap_or(""))?;
+                    export_from_csv(&fs::read_to_string(&cmd.file)?, &mapping, cmd.on_duplicate)?
                 },
-            }
-            if value != 0 {
-                match arg4 {
-                    Some(date) => {
-                        let result = user_data.edit_habit_node(args.clone(), date, value);
-                        println!("{:?}", result);
-                    },
-                    None => {
-                        let today = HabitData::get_current_date_id();
-                        let result = user_data.edit_habit_node(args.clone(), today, value);
-                        println!("{:?}", result);
-                    },
-                }
-            }
-            
+            };
+            user_data.import(export, if cmd.replace { ImportMode::Replace } else { ImportMode::Merge(cmd.on_duplicate) })
         },
-        "history" => {
-            if let Some(habit) = arg2 {
-                let _ = user_data.show_history(habit);
-            }
+        Command::Where(_) | Command::Export(_) | Command::Log(_) | Command::Undo(_) | Command::Redo(_) => {
+            Ok("".to_string())
         },
-        "habit_test" => {
-            let test_habit = HabitData::new("test habit!".to_string(), 1000, None);
-            let _ = user_data.add_habit("test_habit".to_string(), test_habit);
+    }
+}
 
-            println!("Attempting to add a test habit!");
+fn export(store: &dyn storage::Store, cmd: ExportCmd) -> Result<String, HabitError> {
+    let mut user_data = store.load()?;
+    user_data.reconcile(HabitID::today());
+    let options = ExportOptions {
+        from: cmd.from.map(|d| HabitID::parse(&d)).transpose()?,
+        to: cmd.to.map(|d| HabitID::parse(&d)).transpose()?,
+        habits: cmd.habit,
+        wide: cmd.wide,
+    };
+    let text = user_data.export(cmd.format, &options)?;
+
+    match cmd.output {
+        Some(path) => {
+            fs::write(&path, text)?;
+            Ok(format!("Exported to {}", path.display()))
         },
-        "list" => {
-            match arg2 {
-                Some(date) => {
-                    let result = user_data.habit_list_for_day(date);
-                    println!("{:?}", result);
-                },
-                None => {
-                    let today = HabitData::get_current_date_id();
-                    let result = user_data.habit_list_for_day(today);
-                    println!("{:?}", result);
-                },
-            }
-        }
-        _ => {
-            println!("testing hehe");
-            return
-        }
+        None => Ok(text.trim_end().to_string()),
     }
+}
 
-    let command = args[1].clone();
-    println!("{:?}", command);
+// The command as typed, minus the leading --data-dir, to name it in the journal
+fn command_line(args: impl Iterator<Item = String>) -> String {
+    let mut args = args.peekable();
+    while args.peek().map(|arg| arg == "--data-dir").unwrap_or(false) {
+        args.nth(1);
+    }
+    args.map(|arg| if arg.is_empty() || arg.contains(char::is_whitespace) { format!("'{}'", arg) } else { arg })
+        .collect::<Vec<String>>()
+        .join(" ")
+}
 
-    let serialized = bincode::serialize(&user_data).unwrap();
+fn format_from_extension(path: &Path) -> Result<ExportFormat, HabitError> {
+    match path.extension().and_then(|e| e.to_str()).map(|e| e.to_lowercase()).as_deref() {
+        Some("json") => Ok(ExportFormat::Json),
+        Some("toml") => Ok(ExportFormat::Toml),
+        Some("csv") => Ok(ExportFormat::Csv),
+        _ => Err(HabitError::InvalidImport(format!("can't tell the format of {}, pass --format json or --format toml", path.display()))),
+    }
+}
 
-    let mut file = File::create("userdata.bin").unwrap();
-    file.write_all(&serialized).unwrap();
-}
\ No newline at end of file
+fn date_or_today(date: Option<String>) -> Result<HabitID, HabitError> {
+    match date {
+        Some(d) => HabitID::parse(&d),
+        None => Ok(HabitData::get_current_date_id()),
+    }
+}
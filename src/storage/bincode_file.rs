@@ -0,0 +1,39 @@
+use std::path::PathBuf;
+
+use super::{corrupt_file_error, read_file, write_atomic, Store};
+use crate::types::{HabitError, UserData};
+
+// The default backend: the versioned bincode format from types::format in a single file
+pub struct BincodeStore {
+    path: PathBuf,
+}
+
+impl BincodeStore {
+    pub fn new(path: PathBuf) -> Self {
+        BincodeStore { path }
+    }
+}
+
+impl Store for BincodeStore {
+    fn load(&self) -> Result<UserData, HabitError> {
+        match read_file(&self.path)? {
+            Some(data) => {
+                let (user_data, notes) = UserData::from_bytes(&data).map_err(|e| corrupt_file_error(e, &self.path))?;
+                // Only files from before dates were checked have notes; they're dropped the next time it's saved
+                for note in notes {
+                    eprintln!("Note: {} in {}", note, self.path.display());
+                }
+                Ok(user_data)
+            },
+            None => Ok(UserData::new()),
+        }
+    }
+
+    fn save(&self, user_data: &UserData) -> Result<(), HabitError> {
+        write_atomic(&self.path, &user_data.to_bytes()?)
+    }
+
+    fn location(&self) -> String {
+        self.path.display().to_string()
+    }
+}
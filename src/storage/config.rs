@@ -0,0 +1,73 @@
+use std::path::Path;
+use serde::Deserialize;
+
+use super::read_file;
+use crate::types::HabitError;
+
+pub const CONFIG_FILE: &str = "config.toml";
+
+// <data dir>/config.toml, every key is optional:
+//
+//   backend = "bincode"   # or "json", "mongodb"
+//
+//   [mongodb]
+//   uri = "mongodb://localhost:27017"
+//   database = "habit_tracker"
+//   collection = "userdata"
+//
+//   [list]
+//   order = ["walk", "read"]   # habits listed first, in this order; the rest follow by name
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub backend: Backend,
+    pub mongodb: MongoConfig,
+    pub list: ListConfig,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ListConfig {
+    pub order: Vec<String>,
+}
+
+#[derive(Debug, Default, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum Backend {
+    #[default]
+    Bincode,
+    Json,
+    Mongodb,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct MongoConfig {
+    pub uri: String,
+    pub database: String,
+    pub collection: String,
+}
+
+impl Default for MongoConfig {
+    fn default() -> Self {
+        MongoConfig {
+            uri: "mongodb://localhost:27017".to_string(),
+            database: "habit_tracker".to_string(),
+            collection: "userdata".to_string(),
+        }
+    }
+}
+
+impl Config {
+    pub fn load(path: &Path) -> Result<Self, HabitError> {
+        match read_file(path)? {
+            Some(data) => {
+                let text = String::from_utf8(data)
+                    .map_err(|e| HabitError::InvalidConfig(format!("{}: {}", path.display(), e)))?;
+                toml::from_str(&text)
+                    .map_err(|e| HabitError::InvalidConfig(format!("{}: {}", path.display(), e)))
+            },
+            None => Ok(Config::default()),
+        }
+    }
+}
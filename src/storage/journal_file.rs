@@ -0,0 +1,84 @@
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::PathBuf;
+
+use super::{corrupt_file_error, read_file, write_atomic};
+use crate::types::{HabitError, Journal};
+
+// Once the file holds this many records it's rewritten with only the newest KEEP_ENTRIES commands
+const COMPACT_AFTER: usize = 200;
+const KEEP_ENTRIES: usize = 100;
+
+// The undo journal, kept in the data directory whichever backend holds the data. Each command
+// appends its records; the file is only rewritten when it's compacted or was damaged.
+pub struct JournalFile {
+    path: PathBuf,
+}
+
+impl JournalFile {
+    pub fn new(path: PathBuf) -> Self {
+        JournalFile { path }
+    }
+
+    pub fn load(&self) -> Result<Journal, HabitError> {
+        match read_file(&self.path)? {
+            Some(data) => Journal::from_bytes(&data).map_err(|e| corrupt_file_error(e, &self.path)),
+            None => Ok(Journal::default()),
+        }
+    }
+
+    pub fn save(&self, journal: &mut Journal) -> Result<(), HabitError> {
+        if !journal.has_pending() {
+            return Ok(())
+        }
+        if journal.record_count() > COMPACT_AFTER {
+            journal.compact(KEEP_ENTRIES);
+        }
+
+        if journal.needs_rewrite() || !self.path.exists() {
+            write_atomic(&self.path, &journal.to_bytes()?)?;
+        } else {
+            let mut file = OpenOptions::new().append(true).open(&self.path)?;
+            file.write_all(&journal.pending_bytes()?)?;
+            file.sync_data()?;
+        }
+        journal.mark_written();
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::tests::scratch_dir;
+    use crate::types::{HabitData, UserData};
+
+    #[test]
+    fn it_appends_and_compacts() {
+        let dir = scratch_dir("journal");
+        let file = JournalFile::new(dir.join(crate::storage::JOURNAL_FILE));
+
+        let mut user_data = UserData::new();
+        let mut journal = file.load().unwrap();
+        for i in 0..=COMPACT_AFTER {
+            let before = user_data.clone();
+            user_data.add_habit(format!("habit {}", i), HabitData::new("".to_string(), 1.0, None).unwrap()).unwrap();
+            journal.record(&format!("add_habit 'habit {}'", i), &before, &user_data);
+            file.save(&mut journal).unwrap();
+        }
+        let mut journal = file.load().unwrap();
+        assert_eq!(journal.record_count(), KEEP_ENTRIES);
+        assert!(journal.log(1).ends_with(&format!("add_habit 'habit {}'", COMPACT_AFTER)));
+
+        // Appended undos are read back, and the oldest commands are gone
+        journal.undo(&mut user_data, KEEP_ENTRIES + 1).unwrap();
+        file.save(&mut journal).unwrap();
+        assert!(user_data.remove_habit(format!("habit {}", COMPACT_AFTER - KEEP_ENTRIES)).is_ok());
+        assert!(user_data.remove_habit(format!("habit {}", COMPACT_AFTER - KEEP_ENTRIES + 1)).is_err());
+        let journal = file.load().unwrap();
+        assert_eq!(journal.record_count(), KEEP_ENTRIES + 1);
+        assert!(journal.log(1).ends_with("(undone)"));
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
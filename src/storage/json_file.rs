@@ -0,0 +1,78 @@
+use std::path::PathBuf;
+use serde::{Deserialize, Serialize};
+
+use super::{corrupt_file_error, read_file, write_atomic, Store};
+use crate::types::{HabitError, UserData, FORMAT_VERSION, OLDEST_NAMED_FORMAT_VERSION};
+
+// Same data as the bincode store, as pretty-printed JSON so it can be read and diffed
+pub struct JsonStore {
+    path: PathBuf,
+}
+
+#[derive(Serialize, Deserialize)]
+struct JsonFile {
+    format_version: u32,
+    user_data: UserData,
+}
+
+impl JsonStore {
+    pub fn new(path: PathBuf) -> Self {
+        JsonStore { path }
+    }
+}
+
+impl Store for JsonStore {
+    fn load(&self) -> Result<UserData, HabitError> {
+        let data = match read_file(&self.path)? {
+            Some(data) => data,
+            None => return Ok(UserData::new()),
+        };
+
+        let file: JsonFile = serde_json::from_slice(&data)
+            .map_err(|e| corrupt_file_error(HabitError::StorageCorrupt(e.to_string()), &self.path))?;
+        if !(OLDEST_NAMED_FORMAT_VERSION..=FORMAT_VERSION).contains(&file.format_version) {
+            return Err(HabitError::UnsupportedFormat(file.format_version));
+        }
+        Ok(file.user_data)
+    }
+
+    fn save(&self, user_data: &UserData) -> Result<(), HabitError> {
+        let file = JsonFile { format_version: FORMAT_VERSION, user_data: user_data.clone() };
+        let serialized = serde_json::to_vec_pretty(&file)
+            .map_err(|e| HabitError::Io(e.to_string()))?;
+        write_atomic(&self.path, &serialized)
+    }
+
+    fn location(&self) -> String {
+        self.path.display().to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::tests::scratch_dir;
+    use crate::types::{HabitData, HabitID};
+
+    #[test]
+    fn it_round_trips_through_json() {
+        let dir = scratch_dir("json");
+        let store = JsonStore::new(dir.join("userdata.json"));
+
+        let mut user_data = UserData::new();
+        user_data.add_habit("walk".to_string(), HabitData::new("".to_string(), 5000.0, Some("1-3-5".to_string())).unwrap()).unwrap();
+        user_data.edit_habit_node("walk".to_string(), "increment", HabitID::today(), 1200.0, true).unwrap();
+        store.save(&user_data).unwrap();
+
+        let text = std::fs::read_to_string(dir.join("userdata.json")).unwrap();
+        assert!(text.contains("\"format_version\""));
+
+        let mut loaded = store.load().unwrap();
+        assert!(loaded.remove_habit("walk".to_string()).is_ok());
+
+        std::fs::write(dir.join("userdata.json"), &text[..text.len() / 2]).unwrap();
+        assert!(matches!(store.load(), Err(HabitError::StorageCorrupt(_))));
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
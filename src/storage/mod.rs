@@ -0,0 +1,317 @@
+use std::env;
+use std::fs::{self, File, OpenOptions};
+use std::io::{ErrorKind, Read, Write};
+use std::path::{Path, PathBuf};
+
+use crate::types::{HabitError, HabitID, UserData};
+
+mod bincode_file;
+mod config;
+mod journal_file;
+mod json_file;
+mod mongo;
+pub use bincode_file::BincodeStore;
+pub use config::{Backend, Config, MongoConfig, CONFIG_FILE};
+pub use journal_file::JournalFile;
+pub use json_file::JsonStore;
+pub use mongo::MongoStore;
+
+pub const DATA_FILE: &str = "userdata.bin";
+pub const JSON_DATA_FILE: &str = "userdata.json";
+pub const JOURNAL_FILE: &str = "journal.bin";
+pub const DATA_DIR_ENV: &str = "HABIT_TRACKER_DATA";
+
+// Where the tracker keeps its data: --data-dir, then $HABIT_TRACKER_DATA,
+// then $XDG_DATA_HOME/habit-tracker, then ~/.local/share/habit-tracker
+pub fn data_dir(flag: Option<PathBuf>) -> Result<PathBuf, HabitError> {
+    resolve_data_dir(
+        flag,
+        env::var_os(DATA_DIR_ENV).map(PathBuf::from),
+        env::var_os("XDG_DATA_HOME").map(PathBuf::from),
+        env::var_os("HOME").map(PathBuf::from),
+    )
+}
+
+fn resolve_data_dir(flag: Option<PathBuf>, env_dir: Option<PathBuf>, xdg_data_home: Option<PathBuf>, home: Option<PathBuf>) -> Result<PathBuf, HabitError> {
+    let non_empty = |p: &PathBuf| !p.as_os_str().is_empty();
+
+    if let Some(dir) = flag.filter(non_empty).or(env_dir.filter(non_empty)) {
+        return Ok(dir);
+    }
+    // The XDG spec says relative values should be ignored
+    if let Some(xdg) = xdg_data_home.filter(|p| p.is_absolute()) {
+        return Ok(xdg.join("habit-tracker"));
+    }
+    match home.filter(non_empty) {
+        Some(home) => Ok(home.join(".local").join("share").join("habit-tracker")),
+        None => Err(HabitError::Io(format!("can't find a data directory, set --data-dir or ${}", DATA_DIR_ENV))),
+    }
+}
+
+// A change applied to the loaded tracker, returning a message for the user
+pub type Mutation<'a> = Box<dyn FnOnce(&mut UserData) -> Result<String, HabitError> + 'a>;
+
+pub trait Store {
+    fn load(&self) -> Result<UserData, HabitError>;
+    fn save(&self, user_data: &UserData) -> Result<(), HabitError>;
+
+    // Human-readable description of where the data lives, shown by `where`
+    fn location(&self) -> String;
+
+    // Loads, fills in missed days up to `today`, applies `f`, and saves only if `f` succeeded and
+    // changed something. Read-only commands never rewrite the file, so they can't replace the backup
+    // of the last good version; the missed days are filled in again on every load until a save.
+    fn transaction(&self, today: HabitID, f: Mutation<'_>) -> Result<String, HabitError> {
+        let mut user_data = self.load()?;
+        user_data.reconcile(today);
+        let before = user_data.clone();
+        let result = f(&mut user_data)?;
+        if user_data != before {
+            self.save(&user_data)?;
+        }
+        Ok(result)
+    }
+}
+
+// Loads <data dir>/config.toml, or the defaults if there isn't one
+pub fn load_config(dir: &Path) -> Result<Config, HabitError> {
+    Config::load(&dir.join(CONFIG_FILE))
+}
+
+// Opens the backend picked in the config, bincode by default
+pub fn open(dir: &Path, config: &Config) -> Result<Box<dyn Store>, HabitError> {
+    match config.backend {
+        Backend::Bincode => Ok(Box::new(BincodeStore::new(dir.join(DATA_FILE)))),
+        Backend::Json => Ok(Box::new(JsonStore::new(dir.join(JSON_DATA_FILE)))),
+        Backend::Mongodb => Ok(Box::new(MongoStore::connect(&config.mongodb)?)),
+    }
+}
+
+// Reads a whole file, or None if it doesn't exist yet
+fn read_file(path: &Path) -> Result<Option<Vec<u8>>, HabitError> {
+    let mut file = match File::open(path) {
+        Ok(file) => file,
+        Err(e) if e.kind() == ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(e.into()),
+    };
+
+    let mut data = Vec::new();
+    file.read_to_end(&mut data)?;
+    Ok(Some(data))
+}
+
+// Writes to a temp file next to `path`, fsyncs it and renames it into place, so a crash
+// leaves either the old or the new file. The old file is copied to `<path>.bak` the same way first.
+fn write_atomic(path: &Path, serialized: &[u8]) -> Result<(), HabitError> {
+    if let Some(dir) = path.parent() {
+        if !dir.as_os_str().is_empty() {
+            fs::create_dir_all(dir)?;
+        }
+    }
+
+    let tmp = write_temp(path, serialized)?;
+    if let Some(old) = read_file(path)? {
+        let backup = backup_path(path);
+        let result = write_temp(&backup, &old).and_then(|backup_tmp| fs::rename(&backup_tmp, &backup).map_err(|e| {
+            let _ = fs::remove_file(&backup_tmp);
+            e.into()
+        }));
+        if let Err(e) = result {
+            let _ = fs::remove_file(&tmp);
+            return Err(e)
+        }
+    }
+    if let Err(e) = fs::rename(&tmp, path) {
+        let _ = fs::remove_file(&tmp);
+        return Err(e.into())
+    }
+
+    // Make the rename itself durable
+    if let Some(dir) = path.parent() {
+        let dir = if dir.as_os_str().is_empty() { Path::new(".") } else { dir };
+        if let Ok(dir) = File::open(dir) {
+            let _ = dir.sync_all();
+        }
+    }
+    Ok(())
+}
+
+// Writes and fsyncs a temp file next to `path` whose name no other run is using, ex: userdata.bin.1234.0.tmp,
+// so two runs saving at once can't write into each other's temp file
+fn write_temp(path: &Path, serialized: &[u8]) -> Result<PathBuf, HabitError> {
+    let mut attempt = 0;
+    let (tmp, mut file) = loop {
+        let tmp = sibling_path(path, &format!("{}.{}.tmp", std::process::id(), attempt));
+        match OpenOptions::new().write(true).create_new(true).open(&tmp) {
+            Ok(file) => break (tmp, file),
+            Err(e) if e.kind() == ErrorKind::AlreadyExists => attempt += 1,
+            Err(e) => return Err(e.into()),
+        }
+    };
+    if let Err(e) = file.write_all(serialized).and_then(|_| file.sync_all()) {
+        let _ = fs::remove_file(&tmp);
+        return Err(e.into())
+    }
+    Ok(tmp)
+}
+
+// A file that exists but can't be decoded is an error, never an empty tracker:
+// carrying on with empty data would overwrite it on the next save
+fn corrupt_file_error(e: HabitError, path: &Path) -> HabitError {
+    match e {
+        HabitError::StorageCorrupt(msg) if backup_path(path).exists() => {
+            HabitError::StorageCorrupt(format!("{} ({}); the previous version is kept at {}",
+                msg, path.display(), backup_path(path).display()))
+        },
+        HabitError::StorageCorrupt(msg) => HabitError::StorageCorrupt(format!("{} ({}); there is no backup", msg, path.display())),
+        e => e,
+    }
+}
+
+pub fn backup_path(path: &Path) -> PathBuf {
+    sibling_path(path, "bak")
+}
+
+fn sibling_path(path: &Path, extension: &str) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(".");
+    name.push(extension);
+    path.with_file_name(name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::HabitData;
+
+    pub fn scratch_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("habit-tracker-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn it_resolves_the_data_dir() {
+        let p = |s: &str| Some(PathBuf::from(s));
+
+        assert_eq!(resolve_data_dir(p("/flag"), p("/env"), p("/xdg"), p("/home/me")).unwrap(), PathBuf::from("/flag"));
+        assert_eq!(resolve_data_dir(None, p("/env"), p("/xdg"), p("/home/me")).unwrap(), PathBuf::from("/env"));
+        assert_eq!(resolve_data_dir(None, p(""), p("/xdg"), p("/home/me")).unwrap(), PathBuf::from("/xdg/habit-tracker"));
+        assert_eq!(resolve_data_dir(None, None, p("relative"), p("/home/me")).unwrap(), PathBuf::from("/home/me/.local/share/habit-tracker"));
+        assert!(resolve_data_dir(None, None, None, None).is_err());
+    }
+
+    #[test]
+    fn it_saves_atomically_and_keeps_a_backup() {
+        let dir = scratch_dir("save");
+        let path = dir.join("nested").join(DATA_FILE);
+
+        let store = BincodeStore::new(path.clone());
+        assert!(store.load().is_ok());
+
+        // A temp file another run is still writing is left alone
+        let other = sibling_path(&path, &format!("{}.0.tmp", std::process::id()));
+        fs::create_dir_all(other.parent().unwrap()).unwrap();
+        fs::write(&other, b"half written").unwrap();
+
+        let mut user_data = UserData::new();
+        user_data.add_habit("first".to_string(), HabitData::new("".to_string(), 1.0, None).unwrap()).unwrap();
+        store.save(&user_data).unwrap();
+        user_data.add_habit("second".to_string(), HabitData::new("".to_string(), 1.0, None).unwrap()).unwrap();
+        store.save(&user_data).unwrap();
+
+        let leftovers = fs::read_dir(path.parent().unwrap()).unwrap()
+            .filter(|entry| entry.as_ref().unwrap().file_name().to_string_lossy().ends_with(".tmp"))
+            .count();
+        assert_eq!(leftovers, 1);
+        assert_eq!(fs::read(&other).unwrap(), b"half written");
+        assert!(store.load().unwrap().remove_habit("second".to_string()).is_ok());
+        assert!(BincodeStore::new(backup_path(&path)).load().unwrap().remove_habit("second".to_string()).is_err());
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn it_opens_the_configured_backend() {
+        let dir = scratch_dir("open");
+        assert_eq!(open(&dir, &load_config(&dir).unwrap()).unwrap().location(), dir.join(DATA_FILE).display().to_string());
+
+        fs::write(dir.join(CONFIG_FILE), "backend = \"json\"\n\n[list]\norder = [\"walk\"]\n").unwrap();
+        let config = load_config(&dir).unwrap();
+        assert_eq!(open(&dir, &config).unwrap().location(), dir.join(JSON_DATA_FILE).display().to_string());
+        assert_eq!(config.list.order, vec!["walk".to_string()]);
+
+        fs::write(dir.join(CONFIG_FILE), "backend = \"floppy\"\n").unwrap();
+        assert!(matches!(load_config(&dir).err(), Some(HabitError::InvalidConfig(_))));
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn it_only_saves_successful_transactions() {
+        let dir = scratch_dir("transaction");
+        let store = BincodeStore::new(dir.join(DATA_FILE));
+
+        let result = store.transaction(HabitID::today(), Box::new(|user_data| {
+            user_data.add_habit("kept".to_string(), HabitData::new("".to_string(), 1.0, None).unwrap())
+        }));
+        assert!(result.is_ok());
+
+        let result = store.transaction(HabitID::today(), Box::new(|user_data| {
+            user_data.clear_data();
+            user_data.remove_habit("missing".to_string())
+        }));
+        assert!(result.is_err());
+        assert!(store.load().unwrap().remove_habit("kept".to_string()).is_ok());
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn it_leaves_the_file_and_backup_alone_when_nothing_changed() {
+        let dir = scratch_dir("unchanged");
+        let path = dir.join(DATA_FILE);
+        let store = BincodeStore::new(path.clone());
+
+        for name in ["first", "second"] {
+            store.transaction(HabitID::today(), Box::new(move |user_data| {
+                user_data.add_habit(name.to_string(), HabitData::new("".to_string(), 1.0, None).unwrap())
+            })).unwrap();
+        }
+        let saved = fs::read(&path).unwrap();
+        let backup = fs::read(backup_path(&path)).unwrap();
+
+        // A later day's read-only command fills in missed days but doesn't save them
+        let result = store.transaction(HabitID::new(HabitID::today().date() + chrono::Duration::days(30)), Box::new(|_| Ok("".to_string())));
+        assert!(result.is_ok());
+        assert_eq!(fs::read(&path).unwrap(), saved);
+        assert_eq!(fs::read(backup_path(&path)).unwrap(), backup);
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn it_refuses_to_load_a_corrupt_file() {
+        let dir = scratch_dir("corrupt");
+        let path = dir.join(DATA_FILE);
+
+        let mut user_data = UserData::new();
+        user_data.add_habit("first".to_string(), HabitData::new("".to_string(), 1.0, None).unwrap()).unwrap();
+        let bytes = user_data.to_bytes().unwrap();
+        fs::write(&path, &bytes[..bytes.len() - 3]).unwrap();
+
+        match BincodeStore::new(path.clone()).load() {
+            Err(HabitError::StorageCorrupt(msg)) => assert!(msg.ends_with("there is no backup")),
+            other => panic!("expected StorageCorrupt, got {:?}", other.map(|_| ())),
+        }
+
+        fs::write(backup_path(&path), &bytes).unwrap();
+        match BincodeStore::new(path).load() {
+            Err(HabitError::StorageCorrupt(msg)) => assert!(msg.contains("the previous version is kept at")),
+            other => panic!("expected StorageCorrupt, got {:?}", other.map(|_| ())),
+        }
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
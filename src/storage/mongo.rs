@@ -0,0 +1,152 @@
+use bson::{doc, Document};
+use mongodb::sync::{Client, Collection};
+
+use super::{MongoConfig, Store};
+use crate::types::{HabitError, UserData, FORMAT_VERSION, OLDEST_NAMED_FORMAT_VERSION};
+
+// The whole tracker is kept in one document, so every save is a single atomic replace
+const DOCUMENT_ID: &str = "userdata";
+
+// The operations the store needs from a collection, so tests can swap in an in-memory one
+pub trait DocumentCollection {
+    fn find(&self, id: &str) -> Result<Option<Document>, HabitError>;
+    fn replace(&self, id: &str, document: Document) -> Result<(), HabitError>;
+}
+
+impl DocumentCollection for Collection<Document> {
+    fn find(&self, id: &str) -> Result<Option<Document>, HabitError> {
+        self.find_one(doc! { "_id": id }, None).map_err(mongo_error)
+    }
+
+    fn replace(&self, id: &str, document: Document) -> Result<(), HabitError> {
+        let options = mongodb::options::ReplaceOptions::builder().upsert(true).build();
+        self.replace_one(doc! { "_id": id }, document, options).map_err(mongo_error)?;
+        Ok(())
+    }
+}
+
+pub struct MongoStore<C: DocumentCollection = Collection<Document>> {
+    collection: C,
+    location: String,
+}
+
+impl MongoStore {
+    pub fn connect(config: &MongoConfig) -> Result<Self, HabitError> {
+        let client = Client::with_uri_str(&config.uri).map_err(mongo_error)?;
+        let collection = client.database(&config.database).collection::<Document>(&config.collection);
+        let location = format!("{} ({}.{})", config.uri, config.database, config.collection);
+        Ok(MongoStore::with_collection(collection, location))
+    }
+}
+
+impl<C: DocumentCollection> MongoStore<C> {
+    pub fn with_collection(collection: C, location: String) -> Self {
+        MongoStore { collection, location }
+    }
+}
+
+impl<C: DocumentCollection> Store for MongoStore<C> {
+    fn load(&self) -> Result<UserData, HabitError> {
+        let document = match self.collection.find(DOCUMENT_ID)? {
+            Some(document) => document,
+            None => return Ok(UserData::new()),
+        };
+
+        let version = document.get_i64("format_version")
+            .map_err(|e| HabitError::StorageCorrupt(e.to_string()))?;
+        if !(OLDEST_NAMED_FORMAT_VERSION as i64..=FORMAT_VERSION as i64).contains(&version) {
+            return Err(HabitError::UnsupportedFormat(version as u32));
+        }
+
+        let user_data = document.get_document("user_data")
+            .map_err(|e| HabitError::StorageCorrupt(e.to_string()))?;
+        bson::from_document(user_data.clone())
+            .map_err(|e| HabitError::StorageCorrupt(e.to_string()))
+    }
+
+    fn save(&self, user_data: &UserData) -> Result<(), HabitError> {
+        let user_data = bson::to_document(user_data)
+            .map_err(|e| HabitError::Io(e.to_string()))?;
+        let document = doc! {
+            "_id": DOCUMENT_ID,
+            "format_version": FORMAT_VERSION as i64,
+            "user_data": user_data,
+        };
+        self.collection.replace(DOCUMENT_ID, document)
+    }
+
+    fn location(&self) -> String {
+        self.location.clone()
+    }
+}
+
+fn mongo_error(e: mongodb::error::Error) -> HabitError {
+    HabitError::Io(format!("MongoDB: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+    use std::collections::HashMap;
+
+    use super::*;
+    use crate::types::{HabitData, HabitID};
+
+    // Stand-in for a mongod collection so the backend can be tested offline
+    #[derive(Default)]
+    struct MemoryCollection {
+        documents: RefCell<HashMap<String, Document>>,
+    }
+
+    impl DocumentCollection for MemoryCollection {
+        fn find(&self, id: &str) -> Result<Option<Document>, HabitError> {
+            Ok(self.documents.borrow().get(id).cloned())
+        }
+
+        fn replace(&self, id: &str, document: Document) -> Result<(), HabitError> {
+            self.documents.borrow_mut().insert(id.to_string(), document);
+            Ok(())
+        }
+    }
+
+    fn sample_data() -> UserData {
+        let mut user_data = UserData::new();
+        user_data.add_habit("walk".to_string(), HabitData::new("".to_string(), 5000.0, Some("1-3-5".to_string())).unwrap()).unwrap();
+        user_data.edit_habit_node("walk".to_string(), "increment", HabitID::today(), 1200.0, true).unwrap();
+        user_data.add_habit("read".to_string(), HabitData::new("".to_string(), 1.0, None).unwrap()).unwrap();
+        user_data.hide_habit("read".to_string()).unwrap();
+        user_data
+    }
+
+    fn check_round_trip<C: DocumentCollection>(store: &MongoStore<C>) {
+        store.save(&sample_data()).unwrap();
+        let mut loaded = store.load().unwrap();
+        assert!(loaded.remove_habit("walk".to_string()).is_ok());
+        assert!(loaded.remove_habit("read".to_string()).is_ok());
+    }
+
+    #[test]
+    fn it_round_trips_through_a_collection() {
+        let store = MongoStore::with_collection(MemoryCollection::default(), "memory".to_string());
+        assert!(store.load().is_ok());
+        check_round_trip(&store);
+
+        let mut document = store.collection.find(DOCUMENT_ID).unwrap().unwrap();
+        document.insert("format_version", (FORMAT_VERSION + 1) as i64);
+        store.collection.replace(DOCUMENT_ID, document).unwrap();
+        assert_eq!(store.load().err(), Some(HabitError::UnsupportedFormat(FORMAT_VERSION + 1)));
+    }
+
+    // Runs against a real server when HABIT_TRACKER_TEST_MONGO_URI is set, ex: mongodb://localhost:27017
+    #[test]
+    fn it_round_trips_through_mongod() {
+        let uri = match std::env::var("HABIT_TRACKER_TEST_MONGO_URI") {
+            Ok(uri) => uri,
+            Err(_) => return,
+        };
+        let config = MongoConfig { uri, database: "habit_tracker_test".to_string(), collection: format!("userdata_{}", std::process::id()) };
+        let store = MongoStore::connect(&config).unwrap();
+        check_round_trip(&store);
+        let _ = store.collection.drop(None);
+    }
+}
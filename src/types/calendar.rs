@@ -0,0 +1,163 @@
+use chrono::{Datelike, Duration, NaiveDate};
+use ansi_term::Style;
+
+use super::{HabitData, HabitError, HabitID, NodeStatus, Palette, UserData};
+use super::style::status_style;
+
+const WEEKDAY_LABELS: [&str; 7] = ["Mon", "", "Wed", "", "Fri", "", "Sun"];
+// Ten years, already far wider than a terminal
+const MAX_WEEKS: u32 = 520;
+const LEGEND: &str = "█ complete  ▓▒░ partial  ✘ failed  – skipped  · idle  ○ not logged  . unscheduled";
+
+// Days covered by `calendar`: the last `weeks` weeks up to today, or a whole year
+pub fn calendar_range(weeks: Option<u32>, year: Option<i32>, today: HabitID) -> Result<(NaiveDate, NaiveDate), HabitError> {
+    match (weeks, year) {
+        (Some(_), Some(_)) => Err(HabitError::InvalidArgument("--weeks and --year can't be used together".to_string())),
+        (_, Some(year)) if !(1..=9999).contains(&year) => {
+            Err(HabitError::InvalidArgument(format!("--year must be between 1 and 9999, not {}", year)))
+        },
+        (_, Some(year)) => {
+            match (NaiveDate::from_ymd_opt(year, 1, 1), NaiveDate::from_ymd_opt(year, 12, 31)) {
+                (Some(from), Some(to)) => Ok((from, to)),
+                _ => Err(HabitError::InvalidDate(year.to_string())),
+            }
+        },
+        (Some(weeks), None) if weeks > MAX_WEEKS => {
+            Err(HabitError::InvalidArgument(format!("--weeks can be at most {}, use --year for older history", MAX_WEEKS)))
+        },
+        (weeks, None) => {
+            let weeks = weeks.unwrap_or(26).max(1);
+            let monday = today.date() - Duration::days(today.date().weekday().num_days_from_monday() as i64);
+            Ok((monday - Duration::weeks(weeks as i64 - 1), today.date()))
+        },
+    }
+}
+
+impl HabitData {
+    // Glyph and style for one day. Partial days are shaded by how much of the goal was reached,
+    // and days the habit isn't scheduled on are dimmed.
+    fn calendar_cell(&self, day: HabitID, today: HabitID) -> (Style, &'static str) {
+        let scheduled = self.is_scheduled(day);
+        let dim = |style: Style| if scheduled { style } else { style.dimmed() };
+
+        match self.nodes.get(&day) {
+            Some(node) => {
+                let glyph = match node.status {
+                    NodeStatus::COMPLETE => "█",
+                    NodeStatus::PARTIAL => {
+                        let ratio = self.direction.progress(node.value, node.goal);
+                        if ratio >= 2.0 / 3.0 { "▓" } else if ratio >= 1.0 / 3.0 { "▒" } else { "░" }
+                    },
+                    NodeStatus::FAILED => "✘",
+                    NodeStatus::SKIPPED => "–",
+                    NodeStatus::IDLE => "·",
+                };
+                (dim(status_style(Some(&node.status))), glyph)
+            },
+            None if day > today || day < self.start_date() => (Style::new(), " "),
+            None if !self.is_tracked(day) || !self.frequency_on(day).is_daily() => (Style::new().dimmed(), "."),
+            None => (Style::new(), "○"),
+        }
+    }
+
+    // Heatmap with a column per week (Monday first) and a row per weekday, with month names above
+    // the week each month starts in
+    pub fn calendar(&self, from: NaiveDate, to: NaiveDate, today: HabitID, palette: &Palette) -> String {
+        let first_monday = from - Duration::days(from.weekday().num_days_from_monday() as i64);
+        let weeks = ((to - first_monday).num_days() / 7 + 1) as usize;
+
+        let mut months = String::from("    ");
+        for week in 0..weeks {
+            let monday = first_monday + Duration::weeks(week as i64);
+            let starts_month = (0..7).map(|d| monday + Duration::days(d)).find(|d| d.day() == 1 && *d >= from && *d <= to);
+            let label = match (week, starts_month) {
+                (_, Some(first)) => Some(first.format("%b").to_string()),
+                // The first column gets the month it's in, unless the next month's name would crowd it out
+                (0, None) if (first_monday + Duration::weeks(2)).month() == from.month() => Some(from.format("%b").to_string()),
+                _ => None,
+            };
+            // Each week is two characters wide, so skip a label that would run into the previous one
+            let column = 4 + week * 2;
+            if let Some(label) = label {
+                if column == 4 || column > months.len() {
+                    months.push_str(&" ".repeat(column - months.len()));
+                    months.push_str(&label);
+                }
+            }
+        }
+
+        let mut out = vec![months.trim_end().to_string()];
+        for (weekday, label) in WEEKDAY_LABELS.iter().enumerate() {
+            let mut line = format!("{:<4}", label);
+            for week in 0..weeks {
+                let day = first_monday + Duration::days((week * 7 + weekday) as i64);
+                if day < from || day > to {
+                    line.push_str("  ");
+                    continue;
+                }
+                let (style, glyph) = self.calendar_cell(HabitID::new(day), today);
+                line.push_str(&palette.paint(style, glyph));
+                line.push(' ');
+            }
+            out.push(line.trim_end().to_string());
+        }
+        out.push(palette.dim(LEGEND));
+        out.join("\n")
+    }
+}
+
+impl UserData {
+    pub fn show_calendar(&self, habit: String, from: NaiveDate, to: NaiveDate, palette: &Palette) -> Result<String, HabitError> {
+        match self.data.get(&habit) {
+            Some(data) => {
+                let title = palette.bold(&format!("{}: {} to {}", habit, HabitID::new(from), HabitID::new(to)));
+                Ok(format!("{}\n{}", title, data.calendar(from, to, HabitID::today(), palette)))
+            },
+            None => Err(HabitError::HabitNotFound(habit)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::testing::{day, habit_starting};
+
+    #[test]
+    fn it_picks_the_calendar_range() {
+        // 10-4-2023 is a Wednesday
+        let today = day("10-4-2023");
+        assert_eq!(calendar_range(Some(2), None, today).unwrap(), (day("9-25-2023").date(), today.date()));
+        assert_eq!(calendar_range(None, Some(2023), today).unwrap(), (day("1-1-2023").date(), day("12-31-2023").date()));
+        assert!(matches!(calendar_range(Some(2), Some(2023), today), Err(HabitError::InvalidArgument(_))));
+        assert!(matches!(calendar_range(Some(99999999), None, today), Err(HabitError::InvalidArgument(_))));
+        assert!(matches!(calendar_range(None, Some(-262143), today), Err(HabitError::InvalidArgument(_))));
+    }
+
+    #[test]
+    fn it_draws_a_heatmap() {
+        // Scheduled Monday to Friday, starting Wednesday 9-27-2023
+        let mut data = habit_starting("9-27-2023", 10.0, Some("1-2-3-4-5"));
+        data.edit_node(day("9-27-2023"), "complete", 0.0).unwrap();
+        data.edit_node(day("9-28-2023"), "set", 8.0).unwrap();
+        data.edit_node(day("9-29-2023"), "set", 2.0).unwrap();
+        data.edit_node(day("10-1-2023"), "complete", 0.0).unwrap();
+        data.edit_node(day("10-2-2023"), "fail", 0.0).unwrap();
+        data.edit_node(day("10-3-2023"), "skip", 0.0).unwrap();
+
+        let (from, to) = calendar_range(Some(3), None, day("10-4-2023")).unwrap();
+        let calendar = data.calendar(from, to, day("10-4-2023"), &Palette::new(false));
+        let expected = [
+            "      Oct",
+            "Mon     ✘",
+            "        –",
+            "Wed   █ ○",
+            "      ▓",
+            "Fri   ░",
+            "      .",
+            "Sun   █",
+            LEGEND,
+        ];
+        assert_eq!(calendar, expected.join("\n"));
+    }
+}
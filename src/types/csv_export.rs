@@ -0,0 +1,121 @@
+use std::collections::BTreeMap;
+
+use super::{status_name, ExportOptions, HabitError, HabitID, UserData};
+
+// Spreadsheet-friendly exports of the logged days. The long layout has one row per habit per day,
+// the wide layout one row per day with a value column per habit.
+impl UserData {
+    pub fn to_csv(&self, options: &ExportOptions) -> Result<String, HabitError> {
+        let mut writer = csv::Writer::from_writer(Vec::new());
+        write_record(&mut writer, ["habit", "date", "status", "value", "goal", "scheduled"])?;
+
+        for name in self.export_names(options)? {
+            let habit = &self.data[name];
+            for (day, node) in habit.nodes_between(options.from, options.to) {
+                write_record(&mut writer, [
+                    name.clone(),
+                    day.iso(),
+                    status_name(&node.status).to_string(),
+                    node.value.to_string(),
+                    node.goal.to_string(),
+                    habit.is_scheduled(*day).to_string(),
+                ])?;
+            }
+        }
+
+        finish(writer)
+    }
+
+    // Cells hold the logged value, and are left empty on days a habit has nothing logged
+    pub fn to_wide_csv(&self, options: &ExportOptions) -> Result<String, HabitError> {
+        let names = self.export_names(options)?;
+        let mut rows: BTreeMap<HabitID, Vec<String>> = BTreeMap::new();
+
+        for (column, name) in names.iter().enumerate() {
+            for (day, node) in self.data[*name].nodes_between(options.from, options.to) {
+                rows.entry(*day).or_insert_with(|| vec![String::new(); names.len()])[column] = node.value.to_string();
+            }
+        }
+
+        let mut writer = csv::Writer::from_writer(Vec::new());
+        let mut header = vec!["date".to_string()];
+        header.extend(names.iter().map(|name| name.to_string()));
+        write_record(&mut writer, header)?;
+
+        for (day, cells) in rows {
+            let mut record = vec![day.iso()];
+            record.extend(cells);
+            write_record(&mut writer, record)?;
+        }
+
+        finish(writer)
+    }
+}
+
+fn write_record<I, T>(writer: &mut csv::Writer<Vec<u8>>, record: I) -> Result<(), HabitError>
+where
+    I: IntoIterator<Item = T>,
+    T: AsRef<[u8]>,
+{
+    writer.write_record(record).map_err(|e| HabitError::Io(e.to_string()))
+}
+
+fn finish(writer: csv::Writer<Vec<u8>>) -> Result<String, HabitError> {
+    let bytes = writer.into_inner().map_err(|e| HabitError::Io(e.to_string()))?;
+    String::from_utf8(bytes).map_err(|e| HabitError::Io(e.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::testing::day;
+    use crate::types::{ExportFormat, HabitData};
+
+    fn sample_data() -> UserData {
+        let mut user_data = UserData::new();
+        user_data.add_habit("walk, daily".to_string(), HabitData::new("".to_string(), 5000.0, Some("1-3-5".to_string())).unwrap()).unwrap();
+        user_data.edit_habit_node("walk, daily".to_string(), "complete", day("10-2-2023"), 0.0, false).unwrap();
+        user_data.edit_habit_node("walk, daily".to_string(), "set", day("10-4-2023"), 2500.0, false).unwrap();
+        user_data.edit_habit_node("walk, daily".to_string(), "increment", day("10-7-2023"), 800.0, true).unwrap();
+        user_data.add_habit("read".to_string(), HabitData::new("".to_string(), 1.0, None).unwrap()).unwrap();
+        user_data.edit_habit_node("read".to_string(), "skip", day("10-3-2023"), 0.0, false).unwrap();
+        user_data.edit_habit_node("read".to_string(), "complete", day("10-4-2023"), 0.0, false).unwrap();
+        user_data
+    }
+
+    #[test]
+    fn it_writes_one_row_per_habit_per_day() {
+        let csv = sample_data().export(ExportFormat::Csv, &ExportOptions::default()).unwrap();
+        assert_eq!(csv, "\
+habit,date,status,value,goal,scheduled
+read,2023-10-03,skipped,0,1,true
+read,2023-10-04,complete,1,1,true
+\"walk, daily\",2023-10-02,complete,5000,5000,true
+\"walk, daily\",2023-10-04,partial,2500,5000,true
+\"walk, daily\",2023-10-07,partial,800,5000,false
+");
+    }
+
+    #[test]
+    fn it_filters_by_date_and_habit() {
+        let options = ExportOptions { from: Some(day("10-3-2023")), to: Some(day("10-4-2023")), habits: vec!["walk, daily".to_string()], wide: false };
+        let csv = sample_data().to_csv(&options).unwrap();
+        assert_eq!(csv.lines().count(), 2);
+        assert!(csv.contains("2023-10-04,partial"));
+
+        let options = ExportOptions { habits: vec!["swim".to_string()], ..Default::default() };
+        assert_eq!(sample_data().to_csv(&options), Err(HabitError::HabitNotFound("swim".to_string())));
+    }
+
+    #[test]
+    fn it_writes_the_wide_layout() {
+        let options = ExportOptions { to: Some(day("10-4-2023")), wide: true, ..Default::default() };
+        let csv = sample_data().export(ExportFormat::Csv, &options).unwrap();
+        assert_eq!(csv, "\
+date,read,\"walk, daily\"
+2023-10-02,,5000
+2023-10-03,0,
+2023-10-04,1,2500
+");
+    }
+}
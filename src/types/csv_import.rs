@@ -0,0 +1,450 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+use chrono::{Duration, NaiveDate};
+
+use super::{status_name, DuplicatePolicy, Export, ExportDay, ExportHabit, ExportPeriod, Frequency, GoalDirection, HabitError, HabitID, NodeStatus, EXPORT_SCHEMA_VERSION};
+
+// Imports from other apps are turned into an Export, so they go through the same validation and
+// merge rules as `import` of our own files.
+
+// Which columns of a generic CSV file hold what. Unset columns fall back to a column with the
+// same name as the key (date, habit, value, status) when the file has one.
+#[derive(Clone, Debug, Default)]
+pub struct CsvMapping {
+    pub date: Option<String>,
+    pub habit: Option<String>,
+    pub value: Option<String>,
+    pub status: Option<String>,
+    pub habit_name: Option<String>, // used for every row when the file has no habit column
+    pub goal: f64,
+    pub date_format: Option<String>, // chrono format, ex: %d/%m/%Y
+}
+
+impl CsvMapping {
+    // Reads `--map` pairs written as key=column, ex: date=Day,value=Steps
+    pub fn parse_map(&mut self, map: &str) -> Result<(), HabitError> {
+        for pair in map.split(',').filter(|p| !p.trim().is_empty()) {
+            let (key, column) = match pair.split_once('=') {
+                Some((key, column)) => (key.trim().to_lowercase(), column.trim().to_string()),
+                None => return Err(HabitError::InvalidImport(format!("'{}' in --map should look like key=column", pair))),
+            };
+            match key.as_str() {
+                "date" => self.date = Some(column),
+                "habit" => self.habit = Some(column),
+                "value" => self.value = Some(column),
+                "status" => self.status = Some(column),
+                _ => return Err(HabitError::InvalidImport(format!("unknown --map key '{}' (expected date, habit, value or status)", key))),
+            }
+        }
+        Ok(())
+    }
+}
+
+// A parsed CSV file with a header row, looked up by column name
+struct Table {
+    header: Vec<String>,
+    rows: Vec<csv::StringRecord>,
+}
+
+impl Table {
+    fn parse(text: &str, has_header: bool) -> Result<Self, HabitError> {
+        let mut reader = csv::ReaderBuilder::new().has_headers(false).flexible(true).trim(csv::Trim::All).from_reader(text.as_bytes());
+        let mut rows = Vec::new();
+        for record in reader.records() {
+            rows.push(record.map_err(|e| HabitError::InvalidImport(e.to_string()))?);
+        }
+
+        let header = match has_header && !rows.is_empty() {
+            true => rows.remove(0).iter().map(|h| h.to_lowercase()).collect(),
+            false => Vec::new(),
+        };
+        Ok(Table { header, rows })
+    }
+
+    fn column(&self, name: &str) -> Option<usize> {
+        self.header.iter().position(|h| *h == name.to_lowercase())
+    }
+
+    // A column the user asked for has to exist, a default one is only used if it does
+    fn mapped_column(&self, mapped: &Option<String>, default: &str) -> Result<Option<usize>, HabitError> {
+        match mapped {
+            Some(name) => match self.column(name) {
+                Some(i) => Ok(Some(i)),
+                None => Err(HabitError::InvalidImport(format!("the file has no '{}' column", name))),
+            },
+            None => Ok(self.column(default)),
+        }
+    }
+}
+
+fn cell(row: &csv::StringRecord, column: Option<usize>) -> &str {
+    column.and_then(|i| row.get(i)).unwrap_or("")
+}
+
+fn parse_number(text: &str, line: usize) -> Result<f64, HabitError> {
+    text.parse::<f64>().map_err(|_| HabitError::InvalidImport(format!("line {}: '{}' isn't a number", line, text)))
+}
+
+// Status words commonly used by other apps, on top of our own status names
+fn parse_status_word(word: &str) -> Option<NodeStatus> {
+    match word.to_lowercase().as_str() {
+        "complete" | "completed" | "done" | "yes" | "y" | "true" | "x" | "1" => Some(NodeStatus::COMPLETE),
+        "failed" | "fail" | "no" | "n" | "false" | "0" => Some(NodeStatus::FAILED),
+        "skipped" | "skip" => Some(NodeStatus::SKIPPED),
+        "partial" => Some(NodeStatus::PARTIAL),
+        "idle" => Some(NodeStatus::IDLE),
+        _ => None,
+    }
+}
+
+// Status of a logged value when the file doesn't give one; nothing at all short of the goal is a failure
+fn status_for_value(value: f64, goal: f64, direction: &GoalDirection) -> NodeStatus {
+    match direction.status_for(value, goal) {
+        NodeStatus::PARTIAL if value <= 0.0 => NodeStatus::FAILED,
+        status => status,
+    }
+}
+
+fn habit_from_days(name: String, description: String, goal: f64, unit: Option<String>, days: BTreeMap<HabitID, ExportDay>, archived: bool) -> ExportHabit {
+    let start = days.keys().next().copied().unwrap_or_else(HabitID::today);
+
+    // Other apps don't say when a habit was archived, so assume it went quiet after its last logged day
+    let mut hidden_periods = Vec::new();
+    if archived {
+        let last = days.keys().next_back().copied().unwrap_or(start);
+        hidden_periods.push(ExportPeriod { from: HabitID::new(last.date() + Duration::days(1)).iso(), to: None });
+    }
+
+    ExportHabit {
+        name,
+        description,
+        goal,
+        unit,
+        direction: None,
+        min: None,
+        frequency: None,
+        start_date: start.iso(),
+        enabled_days: None,
+        active: !archived,
+        missed: "idle".to_string(),
+        goal_history: Vec::new(),
+        schedule_history: Vec::new(),
+        hidden_periods,
+        days: days.into_values().collect(),
+    }
+}
+
+fn into_export(mut habits: Vec<ExportHabit>) -> Export {
+    habits.sort_by(|a, b| a.name.cmp(&b.name));
+    Export { schema_version: EXPORT_SCHEMA_VERSION, habits }
+}
+
+// Reads a CSV file with one row per logged day. `policy` settles rows that log the same habit
+// on the same day more than once: with Keep the first row wins, with Overwrite the last.
+pub fn export_from_csv(text: &str, mapping: &CsvMapping, policy: DuplicatePolicy) -> Result<Export, HabitError> {
+    let table = Table::parse(text, true)?;
+    let date_col = match table.mapped_column(&mapping.date, "date")? {
+        Some(i) => i,
+        None => return Err(HabitError::InvalidImport("the file has no 'date' column, pick one with --map date=<column>".to_string())),
+    };
+    let habit_col = table.mapped_column(&mapping.habit, "habit")?;
+    let value_col = table.mapped_column(&mapping.value, "value")?;
+    let status_col = table.mapped_column(&mapping.status, "status")?;
+    if habit_col.is_none() && mapping.habit_name.is_none() {
+        return Err(HabitError::InvalidImport("the file has no 'habit' column, pick one with --map habit=<column> or name the habit with --habit".to_string()));
+    }
+    let goal = mapping.goal;
+
+    let mut habits: BTreeMap<String, BTreeMap<HabitID, ExportDay>> = BTreeMap::new();
+    for (i, row) in table.rows.iter().enumerate() {
+        let line = i + 2;
+        let date_text = cell(row, Some(date_col));
+        let date = match &mapping.date_format {
+            Some(format) => NaiveDate::parse_from_str(date_text, format).map(HabitID::new).ok(),
+            None => HabitID::parse(date_text).ok(),
+        };
+        let date = match date {
+            Some(date) => date,
+            None => return Err(HabitError::InvalidImport(format!("line {}: can't read the date '{}'", line, date_text))),
+        };
+
+        let name = match habit_col {
+            Some(_) => cell(row, habit_col).to_string(),
+            None => mapping.habit_name.clone().unwrap_or_default(),
+        };
+        let value = match cell(row, value_col) {
+            "" => None,
+            text => Some(parse_number(text, line)?),
+        };
+        let status = match cell(row, status_col) {
+            "" => None,
+            word => match parse_status_word(word) {
+                Some(status) => Some(status),
+                None => return Err(HabitError::InvalidImport(format!("line {}: unknown status '{}'", line, word))),
+            },
+        };
+
+        let (status, value) = match (status, value) {
+            (Some(status), Some(value)) => (status, value),
+            (Some(NodeStatus::COMPLETE), None) => (NodeStatus::COMPLETE, goal),
+            (Some(status), None) => (status, 0.0),
+            (None, Some(value)) => (status_for_value(value, goal, &GoalDirection::AtLeast), value),
+            (None, None) => (NodeStatus::COMPLETE, goal),
+        };
+
+        let day = ExportDay { date: date.iso(), status: status_name(&status).to_string(), value, goal };
+        let days = habits.entry(name.clone()).or_default();
+        let take = match days.get(&date) {
+            Some(existing) => policy.prefers_incoming(&name, date, existing.value, value)?,
+            None => true,
+        };
+        if take {
+            days.insert(date, day);
+        }
+    }
+
+    Ok(into_export(habits.into_iter().map(|(name, days)| habit_from_days(name, String::new(), goal, None, days, false)).collect()))
+}
+
+// One row of Loop Habit Tracker's Habits.csv
+struct LoopHabit {
+    position: String,
+    name: String,
+    description: String,
+    numeric: bool,
+    goal: f64,
+    unit: Option<String>,
+    direction: GoalDirection,
+    frequency: Frequency,
+    archived: bool,
+}
+
+// Loop's Habits.csv has changed columns over the years, so they are looked up by name.
+// Older exports only have Position, Name, Description and a few frequency columns; newer ones add
+// Question, Type (0 yes/no, 1 numeric), Unit, Target Type (0 at least, 1 at most), Target Value and Archived?.
+// The frequency is FrequencyNumerator times per FrequencyDenominator days.
+fn loop_habits(text: &str) -> Result<Vec<LoopHabit>, HabitError> {
+    let table = Table::parse(text, true)?;
+    let (position, name) = match (table.column("position"), table.column("name")) {
+        (Some(position), Some(name)) => (position, name),
+        _ => return Err(HabitError::InvalidImport("Habits.csv needs Position and Name columns".to_string())),
+    };
+    let description = table.column("description");
+    let question = table.column("question");
+    let kind = table.column("type");
+    let target = table.column("target value");
+    let target_type = table.column("target type");
+    let numerator = table.column("frequencynumerator");
+    let denominator = table.column("frequencydenominator");
+    let unit = table.column("unit");
+    let archived = table.column("archived?");
+
+    let mut habits = Vec::new();
+    for (i, row) in table.rows.iter().enumerate() {
+        let numeric = cell(row, kind) == "1";
+        let goal = match numeric {
+            true => Some(parse_number(cell(row, target), i + 2)?).filter(|goal| *goal > 0.0).unwrap_or(1.0),
+            false => 1.0,
+        };
+        let description = match cell(row, description) {
+            "" => cell(row, question),
+            text => text,
+        };
+        habits.push(LoopHabit {
+            position: cell(row, Some(position)).to_string(),
+            name: cell(row, Some(name)).to_string(),
+            description: description.to_string(),
+            numeric,
+            goal,
+            unit: Some(cell(row, unit).to_string()).filter(|unit| numeric && !unit.is_empty()),
+            direction: if numeric && cell(row, target_type) == "1" { GoalDirection::AtMost } else { GoalDirection::AtLeast },
+            frequency: loop_frequency(cell(row, numerator), cell(row, denominator)),
+            archived: cell(row, archived).eq_ignore_ascii_case("true"),
+        });
+    }
+    Ok(habits)
+}
+
+// Weeks and months are 7 and 30 or 31 days in Loop. Anything that can't be read, or is at least
+// once a day, is daily.
+fn loop_frequency(numerator: &str, denominator: &str) -> Frequency {
+    let (times, days) = match (numerator.parse::<u32>(), denominator.parse::<u32>()) {
+        (Ok(times), Ok(days)) if times < days => (times, days),
+        _ => return Frequency::default(),
+    };
+    let text = match days {
+        7 => format!("{}/week", times),
+        30 | 31 => format!("{}/month", times),
+        days => format!("{}/{}days", times, days),
+    };
+    Frequency::parse(&text).unwrap_or_default()
+}
+
+// Checkmarks.csv rows are `date,value` without a header. Yes/no habits use 2 for done, 1 for days
+// not needed because of the habit's frequency, 0 for not done, 3 for skipped and -1 for unknown.
+// Numeric habits store the amount times 1000, and -1 for unknown. Days not needed are skipped for
+// daily habits and left out for the others, which are judged per period anyway.
+fn loop_checkmarks(habit: &LoopHabit, text: &str) -> Result<BTreeMap<HabitID, ExportDay>, HabitError> {
+    let table = Table::parse(text, false)?;
+    let mut days = BTreeMap::new();
+
+    for (i, row) in table.rows.iter().enumerate() {
+        let date = match NaiveDate::parse_from_str(cell(row, Some(0)), "%Y-%m-%d") {
+            Ok(date) => HabitID::new(date),
+            Err(_) if i == 0 => continue, // header row
+            Err(_) => return Err(HabitError::InvalidImport(format!("{} Checkmarks.csv line {}: can't read the date '{}'", habit.name, i + 1, cell(row, Some(0))))),
+        };
+        let raw = parse_number(cell(row, Some(1)), i + 1)?;
+
+        let (status, value) = match (habit.numeric, raw as i64) {
+            (_, -1) => continue,
+            (false, 1) if !habit.frequency.is_daily() => continue,
+            (false, 2) => (NodeStatus::COMPLETE, 1.0),
+            (false, 1) | (false, 3) => (NodeStatus::SKIPPED, 0.0),
+            (false, _) => (NodeStatus::FAILED, 0.0),
+            (true, _) => {
+                let value = raw / 1000.0;
+                (status_for_value(value, habit.goal, &habit.direction), value)
+            },
+        };
+        days.insert(date, ExportDay { date: date.iso(), status: status_name(&status).to_string(), value, goal: habit.goal });
+    }
+    Ok(days)
+}
+
+// Reads an unzipped Loop Habit Tracker CSV export: Habits.csv plus one folder per habit named
+// after its position and name, ex: "001 Meditate/Checkmarks.csv"
+pub fn export_from_loop(dir: &Path) -> Result<Export, HabitError> {
+    let habits = loop_habits(&read_text(&dir.join("Habits.csv"))?)?;
+    let folders = fs::read_dir(dir)?
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.path().is_dir())
+        .map(|entry| entry.file_name().to_string_lossy().to_string())
+        .collect::<Vec<String>>();
+
+    let mut exported = Vec::new();
+    for habit in habits {
+        let prefix = format!("{:0>3} ", habit.position);
+        let days = match folders.iter().find(|folder| folder.starts_with(&prefix)) {
+            Some(folder) => loop_checkmarks(&habit, &read_text(&dir.join(folder).join("Checkmarks.csv"))?)?,
+            None => BTreeMap::new(),
+        };
+        exported.push(ExportHabit {
+            direction: Some(habit.direction.name().to_string()).filter(|_| habit.direction != GoalDirection::AtLeast),
+            frequency: Some(habit.frequency.name()).filter(|_| !habit.frequency.is_daily()),
+            ..habit_from_days(habit.name.clone(), habit.description.clone(), habit.goal, habit.unit.clone(), days, habit.archived)
+        });
+    }
+    Ok(into_export(exported))
+}
+
+fn read_text(path: &Path) -> Result<String, HabitError> {
+    fs::read_to_string(path).map_err(|e| HabitError::Io(format!("{}: {}", path.display(), e)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::testing::day;
+    use crate::types::{ImportMode, UserData, HabitData};
+
+    #[test]
+    fn it_reads_a_loop_export() {
+        let dir = std::env::temp_dir().join(format!("habit-tracker-loop-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(dir.join("001 Meditate")).unwrap();
+        fs::create_dir_all(dir.join("002 Run")).unwrap();
+        fs::create_dir_all(dir.join("003 Coffee")).unwrap();
+        fs::create_dir_all(dir.join("004 Stretch")).unwrap();
+        fs::write(dir.join("Habits.csv"), "\
+Position,Name,Type,Question,Description,FrequencyNumerator,FrequencyDenominator,Color,Unit,Target Type,Target Value,Archived?
+001,Meditate,0,Did you meditate today?,,1,1,#FF8F00,,,0,false
+002,Run,1,How far did you run?,km,1,1,#FF8F00,km,0,5,true
+003,Coffee,1,How many coffees?,,1,1,#FF8F00,cups,1,2,false
+004,Stretch,0,,,3,7,#FF8F00,,,0,false
+").unwrap();
+        fs::write(dir.join("001 Meditate").join("Checkmarks.csv"), "2023-10-01,2\n2023-10-02,0\n2023-10-03,3\n2023-10-04,-1\n2023-10-05,1\n").unwrap();
+        fs::write(dir.join("002 Run").join("Checkmarks.csv"), "2023-10-01,5200\n2023-10-02,2000\n").unwrap();
+        fs::write(dir.join("003 Coffee").join("Checkmarks.csv"), "2023-10-01,1000\n2023-10-02,3000\n").unwrap();
+        fs::write(dir.join("004 Stretch").join("Checkmarks.csv"), "2023-10-02,2\n2023-10-03,1\n2023-10-04,2\n").unwrap();
+
+        let export = export_from_loop(&dir).unwrap();
+        let _ = fs::remove_dir_all(&dir);
+
+        let mut user_data = UserData::new();
+        user_data.import(export, ImportMode::Replace).unwrap();
+        let text = user_data.to_csv(&Default::default()).unwrap();
+        assert_eq!(text, "\
+habit,date,status,value,goal,scheduled
+Coffee,2023-10-01,complete,1,2,true
+Coffee,2023-10-02,failed,3,2,true
+Meditate,2023-10-01,complete,1,1,true
+Meditate,2023-10-02,failed,0,1,true
+Meditate,2023-10-03,skipped,0,1,true
+Meditate,2023-10-05,skipped,0,1,true
+Run,2023-10-01,complete,5.2,5,true
+Run,2023-10-02,partial,2,5,true
+Stretch,2023-10-02,complete,1,1,true
+Stretch,2023-10-04,complete,1,1,true
+");
+
+        let exported = user_data.to_export(&Default::default()).unwrap();
+        assert_eq!(exported.habits[0].direction.as_deref(), Some("at-most"));
+        assert_eq!(exported.habits[1].description, "Did you meditate today?");
+        assert!(!exported.habits[2].active);
+        assert_eq!(exported.habits[2].hidden_periods[0].from, "2023-10-03");
+        assert_eq!(exported.habits[2].unit.as_deref(), Some("km"));
+        assert_eq!(exported.habits[2].direction, None);
+        assert_eq!(exported.habits[3].frequency.as_deref(), Some("3/week"));
+    }
+
+    #[test]
+    fn it_maps_generic_csv_columns() {
+        let text = "Day,Steps,Note\n04/10/2023,5000,\n05/10/2023,2500,\n05/10/2023,3000,\n06/10/2023,,\n";
+        let mut mapping = CsvMapping { habit_name: Some("walk".to_string()), goal: 5000.0, date_format: Some("%d/%m/%Y".to_string()), ..Default::default() };
+        mapping.parse_map("date=Day,value=Steps").unwrap();
+
+        let export = export_from_csv(text, &mapping, DuplicatePolicy::Max).unwrap();
+        let days = &export.habits[0].days;
+        assert_eq!(days.len(), 3);
+        assert_eq!((days[1].status.as_str(), days[1].value), ("partial", 3000.0));
+        assert_eq!((days[2].status.as_str(), days[2].value), ("complete", 5000.0));
+
+        let export = export_from_csv(text, &mapping, DuplicatePolicy::Keep).unwrap();
+        assert_eq!(export.habits[0].days[1].value, 2500.0);
+        assert!(matches!(export_from_csv(text, &mapping, DuplicatePolicy::Error), Err(HabitError::NodeExists(_))));
+
+        mapping.parse_map("status=Result").unwrap();
+        assert!(matches!(export_from_csv(text, &mapping, DuplicatePolicy::Keep), Err(HabitError::InvalidImport(_))));
+    }
+
+    #[test]
+    fn it_applies_the_duplicate_policy_when_merging() {
+        let text = "habit,date,status\nread,2023-10-04,failed\nread,2023-10-05,done\n";
+        let mapping = CsvMapping { goal: 1.0, ..Default::default() };
+
+        for (policy, expected, imported) in [
+            (DuplicatePolicy::Keep, NodeStatus::COMPLETE, "Imported 1 habit with 1 day"),
+            (DuplicatePolicy::Overwrite, NodeStatus::FAILED, "Imported 1 habit with 2 days"),
+        ] {
+            let mut user_data = UserData::new();
+            user_data.add_habit("read".to_string(), HabitData::new("".to_string(), 1.0, None).unwrap()).unwrap();
+            user_data.edit_habit_node("read".to_string(), "complete", day("10-4-2023"), 0.0, false).unwrap();
+
+            let export = export_from_csv(text, &mapping, policy).unwrap();
+            let message = user_data.import(export, ImportMode::Merge(policy)).unwrap();
+            assert_eq!(message, imported, "{:?}", policy);
+            let exported = user_data.to_export(&Default::default()).unwrap();
+            assert_eq!(exported.habits[0].days.len(), 2);
+            assert_eq!(exported.habits[0].days[0].status, status_name(&expected));
+        }
+
+        let mut user_data = UserData::new();
+        user_data.add_habit("read".to_string(), HabitData::new("".to_string(), 1.0, None).unwrap()).unwrap();
+        user_data.edit_habit_node("read".to_string(), "complete", day("10-4-2023"), 0.0, false).unwrap();
+        let export = export_from_csv(text, &mapping, DuplicatePolicy::Error).unwrap();
+        assert!(matches!(user_data.import(export, ImportMode::Merge(DuplicatePolicy::Error)), Err(HabitError::NodeExists(_))));
+        assert_eq!(user_data.to_export(&Default::default()).unwrap().habits[0].days.len(), 1);
+    }
+}
@@ -0,0 +1,152 @@
+use std::fmt;
+use std::num::ParseIntError;
+use chrono::{Datelike, Duration, NaiveDate, Weekday};
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+
+use super::HabitError;
+
+// Dates typed in are limited to four-digit years, so a two-digit year or a huge one is a typo
+const FIRST_YEAR: i32 = 1000;
+const LAST_YEAR: i32 = 9999;
+
+// A single calendar day. Used as the key for a habit's nodes, so ordering is chronological.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct HabitID(NaiveDate);
+
+impl HabitID {
+    pub fn new(date: NaiveDate) -> Self {
+        HabitID(date)
+    }
+
+    pub fn today() -> Self {
+        HabitID(chrono::Local::now().date_naive())
+    }
+
+    pub fn date(&self) -> NaiveDate {
+        self.0
+    }
+
+    // YYYY-MM-DD, the form used in stored and exported data
+    pub fn iso(&self) -> String {
+        self.0.format("%Y-%m-%d").to_string()
+    }
+
+    // Parses the month-day-year form used on the command line, ex: 10-4-2023 or 10-04-2023
+    pub fn from_string(val: String) -> Result<Self, HabitError> {
+        let parts = val.split('-').map(|p| p.parse::<u32>()).collect::<Vec<Result<u32, ParseIntError>>>();
+
+        match parts.as_slice() {
+            [Ok(month), Ok(day), Ok(year)] => {
+                match i32::try_from(*year).ok().and_then(|year| NaiveDate::from_ymd_opt(year, *month, *day)).and_then(HabitID::typed) {
+                    Some(id) => Ok(id),
+                    None => Err(HabitError::InvalidDate(val)),
+                }
+            },
+            _ => {
+                Err(HabitError::InvalidDate(val))
+            }
+        }
+    }
+
+    // Parses any date expression accepted on the command line, relative to today
+    pub fn parse(expr: &str) -> Result<Self, HabitError> {
+        HabitID::parse_relative_to(expr, chrono::Local::now().date_naive())
+    }
+
+    // Accepts M-D-YYYY, ISO YYYY-MM-DD, today, yesterday, -N (N days ago)
+    // and weekday names (mon, monday, ...) meaning the most recent such day, today included.
+    pub fn parse_relative_to(expr: &str, today: NaiveDate) -> Result<Self, HabitError> {
+        let lower = expr.trim().to_lowercase();
+
+        match lower.as_str() {
+            "today" => return Ok(HabitID(today)),
+            "yesterday" => return Ok(HabitID(today - Duration::days(1))),
+            _ => {},
+        }
+
+        if let Some(days) = lower.strip_prefix('-') {
+            // Far enough back, ex: -4000000000, runs off the start of the calendar
+            return match days.parse::<u32>().ok().and_then(|n| today.checked_sub_signed(Duration::days(n as i64))).and_then(HabitID::typed) {
+                Some(id) => Ok(id),
+                None => Err(HabitError::InvalidDate(expr.to_string())),
+            }
+        }
+
+        if let Ok(weekday) = lower.parse::<Weekday>() {
+            let back = (today.weekday().num_days_from_monday() + 7 - weekday.num_days_from_monday()) % 7;
+            return Ok(HabitID(today - Duration::days(back as i64)));
+        }
+
+        if let Ok(date) = NaiveDate::parse_from_str(&lower, "%Y-%m-%d") {
+            return HabitID::typed(date).ok_or_else(|| HabitError::InvalidDate(expr.to_string()));
+        }
+
+        HabitID::from_string(expr.to_string())
+    }
+
+    fn typed(date: NaiveDate) -> Option<Self> {
+        (FIRST_YEAR..=LAST_YEAR).contains(&date.year()).then_some(HabitID(date))
+    }
+
+    // Parses a stored key: ISO, or the "month-day-year" form older files used
+    pub fn from_stored(s: &str) -> Result<Self, HabitError> {
+        match NaiveDate::parse_from_str(s, "%Y-%m-%d") {
+            Ok(date) => Ok(HabitID(date)),
+            Err(_) => HabitID::from_string(s.to_string()),
+        }
+    }
+}
+
+impl fmt::Display for HabitID {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}-{}-{}", self.0.month(), self.0.day(), self.0.year())
+    }
+}
+
+// Stored as an ISO date string. Older files keyed nodes by "month-day-year" strings,
+// so those are still accepted when reading.
+impl Serialize for HabitID {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.iso())
+    }
+}
+
+impl<'de> Deserialize<'de> for HabitID {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        HabitID::from_stored(&s).map_err(de::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_parses_date_expressions() {
+        // a Wednesday
+        let today = NaiveDate::from_ymd_opt(2023, 10, 4).unwrap();
+        let parse = |s: &str| HabitID::parse_relative_to(s, today);
+        let day = |y: i32, m: u32, d: u32| Ok(HabitID(NaiveDate::from_ymd_opt(y, m, d).unwrap()));
+
+        assert_eq!(parse("today"), Ok(HabitID(today)));
+        assert_eq!(parse("Yesterday"), day(2023, 10, 3));
+        assert_eq!(parse("-3"), day(2023, 10, 1));
+        assert_eq!(parse("2023-09-30"), day(2023, 9, 30));
+        assert_eq!(parse("9-30-2023"), day(2023, 9, 30));
+        assert_eq!(parse("mon"), day(2023, 10, 2));
+        assert_eq!(parse("thursday"), day(2023, 9, 28));
+        assert_eq!(parse("wed"), Ok(HabitID(today)));
+
+        assert!(parse("-x").is_err());
+        assert!(parse("2023-02-30").is_err());
+        assert!(parse("someday").is_err());
+        assert_eq!(parse("-4000000000"), Err(HabitError::InvalidDate("-4000000000".to_string())));
+
+        // Years need four digits
+        for expr in ["1-1-4294967295", "1-1-2147483648", "1-1-23", "10-4-10000", "0999-12-31", "+10000-01-01"] {
+            assert_eq!(parse(expr), Err(HabitError::InvalidDate(expr.to_string())), "{}", expr);
+        }
+        assert_eq!(parse("12-31-9999"), day(9999, 12, 31));
+    }
+}
@@ -0,0 +1,149 @@
+use serde::{Deserialize, Serialize};
+
+use super::{format_number, HabitError, NodeStatus, Unit};
+
+// Which side of the goal counts as done. The goal is the target for at-least and exact habits,
+// the limit for at-most habits, and the top of the range for range habits.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum GoalDirection {
+    #[default]
+    AtLeast,    // ex: walk 5000 steps, less is partial
+    AtMost,     // ex: at most 2 coffees, more is failed
+    Exact,      // less is partial, more is failed
+    Range(f64), // the lowest amount that counts, ex: sleep 7 to 9 h; less is partial, more than the goal failed
+}
+
+impl GoalDirection {
+    // Reads a direction by name; a range needs its lowest amount and the other directions take none
+    pub fn from_name(name: &str, min: Option<f64>) -> Result<GoalDirection, HabitError> {
+        let direction = match name.trim().to_lowercase().replace('_', "-").as_str() {
+            "at-least" | "atleast" => GoalDirection::AtLeast,
+            "at-most" | "atmost" => GoalDirection::AtMost,
+            "exact" | "exactly" => GoalDirection::Exact,
+            "range" => match min {
+                Some(min) if min >= 0.0 && min.is_finite() => return Ok(GoalDirection::Range(min)),
+                Some(min) => return Err(HabitError::InvalidArgument(format!("the lowest amount of a range can't be {}", min))),
+                None => return Err(HabitError::InvalidArgument("a range needs its lowest amount, ex: --min 7".to_string())),
+            },
+            _ => return Err(HabitError::InvalidArgument(format!("unknown direction '{}', expected at-least, at-most, exact or range", name))),
+        };
+        match min {
+            Some(_) => Err(HabitError::InvalidArgument(format!("only a range takes a lowest amount, not {}", direction.name()))),
+            None => Ok(direction),
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            GoalDirection::AtLeast => "at-least",
+            GoalDirection::AtMost => "at-most",
+            GoalDirection::Exact => "exact",
+            GoalDirection::Range(_) => "range",
+        }
+    }
+
+    pub fn min(&self) -> Option<f64> {
+        match self {
+            GoalDirection::Range(min) => Some(*min),
+            _ => None,
+        }
+    }
+
+    // A range's lowest amount must stay below every goal it's used with
+    pub fn check_goal(&self, goal: f64) -> Result<(), HabitError> {
+        match self {
+            GoalDirection::Range(min) if *min >= goal => {
+                Err(HabitError::InvalidArgument(format!("the goal must be above the range's lowest amount, {}", min)))
+            },
+            _ => Ok(()),
+        }
+    }
+
+    // The status a logged value earns against the goal
+    pub fn status_for(&self, value: f64, goal: f64) -> NodeStatus {
+        let (low, high) = match self {
+            GoalDirection::AtLeast => (goal, f64::INFINITY),
+            GoalDirection::AtMost => (f64::NEG_INFINITY, goal),
+            GoalDirection::Exact => (goal, goal),
+            GoalDirection::Range(min) => (*min, goal),
+        };
+        if value > high {
+            NodeStatus::FAILED
+        } else if value < low {
+            NodeStatus::PARTIAL
+        } else {
+            NodeStatus::COMPLETE
+        }
+    }
+
+    // The value a day marked complete is given: the goal, or as close to it as the logged value allows
+    pub fn completed_value(&self, value: f64, goal: f64) -> f64 {
+        match self {
+            GoalDirection::AtLeast | GoalDirection::Exact => goal,
+            GoalDirection::AtMost => value.min(goal),
+            GoalDirection::Range(min) => value.clamp(*min, goal),
+        }
+    }
+
+    // How far a partial day got toward the amount that would complete it, 0 to 1
+    pub fn progress(&self, value: f64, goal: f64) -> f64 {
+        let target = match self {
+            GoalDirection::Range(min) => *min,
+            _ => goal,
+        };
+        match target > 0.0 {
+            true => (value / target).clamp(0.0, 1.0),
+            false => 1.0,
+        }
+    }
+
+    // At-most habits are done by not doing something, so a day with nothing logged stayed under the limit
+    pub fn unlogged_is_done(&self) -> bool {
+        *self == GoalDirection::AtMost
+    }
+
+    // The goal as a sentence fragment, ex: 5 km, at most 2, exactly 8 h, 7 to 9 h
+    pub fn describe(&self, goal: f64, unit: &Unit) -> String {
+        match self {
+            GoalDirection::AtLeast => unit.format(goal),
+            GoalDirection::AtMost => format!("at most {}", unit.format(goal)),
+            GoalDirection::Exact => format!("exactly {}", unit.format(goal)),
+            GoalDirection::Range(min) => format!("{} to {}", format_number(*min), unit.format(goal)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_judges_values_by_direction() {
+        let cases = [
+            (GoalDirection::AtLeast, [NodeStatus::PARTIAL, NodeStatus::COMPLETE, NodeStatus::COMPLETE]),
+            (GoalDirection::AtMost, [NodeStatus::COMPLETE, NodeStatus::COMPLETE, NodeStatus::FAILED]),
+            (GoalDirection::Exact, [NodeStatus::PARTIAL, NodeStatus::COMPLETE, NodeStatus::FAILED]),
+            (GoalDirection::Range(1.0), [NodeStatus::COMPLETE, NodeStatus::COMPLETE, NodeStatus::FAILED]),
+        ];
+        for (direction, expected) in cases {
+            assert_eq!([1.5, 2.0, 2.5].map(|value| direction.status_for(value, 2.0)), expected, "{:?}", direction);
+        }
+        assert_eq!(GoalDirection::Range(7.0).status_for(6.5, 9.0), NodeStatus::PARTIAL);
+        assert_eq!(GoalDirection::Range(7.0).progress(3.5, 9.0), 0.5);
+        assert_eq!(GoalDirection::AtMost.completed_value(3.0, 2.0), 2.0);
+        assert_eq!(GoalDirection::Range(7.0).completed_value(0.0, 9.0), 7.0);
+    }
+
+    #[test]
+    fn it_reads_directions() {
+        assert_eq!(GoalDirection::from_name("At_Most", None), Ok(GoalDirection::AtMost));
+        assert_eq!(GoalDirection::from_name("range", Some(7.0)), Ok(GoalDirection::Range(7.0)));
+        assert!(GoalDirection::from_name("range", None).is_err());
+        assert!(GoalDirection::from_name("exact", Some(1.0)).is_err());
+        assert!(GoalDirection::from_name("sideways", None).is_err());
+        assert!(GoalDirection::Range(9.0).check_goal(9.0).is_err());
+        assert_eq!(GoalDirection::Range(7.0).describe(9.0, &Unit::Hours), "7 to 9 h");
+        assert_eq!(GoalDirection::AtMost.describe(2.0, &Unit::Count), "at most 2");
+    }
+}
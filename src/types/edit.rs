@@ -0,0 +1,291 @@
+use super::{format_number, plural, Frequency, GoalDirection, HabitData, HabitError, HabitID, Unit, UserData};
+
+// Changes `edit_habit` makes to one habit; fields left as None stay as they are
+#[derive(Clone, Debug, Default)]
+pub struct HabitEdit {
+    pub rename: Option<String>,
+    pub description: Option<String>,
+    pub days: Option<String>,         // ISO weekday numbers like 1-3-5, or "all" for every day
+    pub goal: Option<String>,         // read in the habit's unit, the new one if that changes too
+    pub from: Option<HabitID>,        // first day of the new goal, days or frequency, defaults to today
+    pub recalculate: bool,            // move days already logged from goal_from on to the new goal
+    pub unit: Option<String>,         // unit name or label, "none" for a plain count
+    pub direction: Option<String>,    // at-least, at-most, exact or range
+    pub min: Option<String>,          // lowest amount of a range, read like the goal
+    pub frequency: Option<String>,    // daily, or how many times per week, month or number of days, ex: 3/week
+}
+
+pub(super) fn describe_days(days: &Option<Vec<u32>>) -> String {
+    match days {
+        Some(days) => days.iter().map(|d| d.to_string()).collect::<Vec<String>>().join("-"),
+        None => "every day".to_string(),
+    }
+}
+
+fn describe_direction(direction: &GoalDirection, unit: &Unit) -> String {
+    match direction.min() {
+        Some(min) => format!("{} from {}", direction.name(), unit.format(min)),
+        None => direction.name().to_string(),
+    }
+}
+
+impl HabitData {
+    // Applies `edit` to this habit, returning a line per change. Checks every field before
+    // changing anything, so a bad value leaves the habit as it was.
+    fn apply_edit(&mut self, edit: &HabitEdit, today: HabitID) -> Result<Vec<String>, HabitError> {
+        let enabled_days = match edit.days.as_deref() {
+            Some("all") => Some(None),
+            Some(days) => Some(Some(HabitData::validate_allowed_days(days.to_string())?)),
+            None => None,
+        };
+        if edit.goal.is_none() && edit.recalculate {
+            return Err(HabitError::InvalidArgument("--recalculate needs --goal".to_string()))
+        }
+        if edit.from.is_some() && edit.goal.is_none() && enabled_days.is_none() && edit.frequency.is_none() {
+            return Err(HabitError::InvalidArgument("--from needs --goal, --days or --frequency".to_string()))
+        }
+        let from = edit.from.unwrap_or(today);
+        let frequency = edit.frequency.as_deref().map(Frequency::parse).transpose()?;
+        let unit = edit.unit.as_deref().map(Unit::from_name).filter(|unit| *unit != self.unit);
+        let goal = match &edit.goal {
+            Some(goal) => Some(unit.as_ref().unwrap_or(&self.unit).parse_amount(goal)?),
+            None => None,
+        };
+        if let Some(goal) = goal.filter(|goal| *goal <= 0.0) {
+            return Err(HabitError::InvalidArgument(format!("goal must be positive, got {}", format_number(goal))))
+        }
+        // --min alone moves the lowest amount of a habit that's already a range
+        let direction = match (edit.direction.as_deref(), &edit.min) {
+            (None, None) => None,
+            (name, min) => {
+                let min = match min {
+                    Some(min) => Some(unit.as_ref().unwrap_or(&self.unit).parse_amount(min)?),
+                    None => None,
+                };
+                Some(GoalDirection::from_name(name.unwrap_or(self.direction.name()), min)?)
+            },
+        };
+
+        let mut changes = Vec::new();
+        if let Some(description) = &edit.description {
+            if *description != self.description {
+                changes.push(format!("description: '{}' -> '{}'", self.description, description));
+                self.description = description.clone();
+            }
+        }
+        // Days before `from` keep the schedule they had, so only the days from then to yesterday are judged again
+        let schedule_from = from.max(self.start_date());
+        let (old_days, old_frequency) = (self.days_on(schedule_from).clone(), self.frequency_on(schedule_from));
+        let days = enabled_days.unwrap_or_else(|| old_days.clone());
+        let frequency = frequency.unwrap_or(old_frequency);
+        if days != old_days || frequency != old_frequency {
+            if days != old_days {
+                changes.push(format!("days: {} -> {} from {}", describe_days(&old_days), describe_days(&days), schedule_from));
+            }
+            if frequency != old_frequency {
+                changes.push(format!("frequency: {} -> {} from {}", old_frequency.name(), frequency.name(), schedule_from));
+            }
+            let judged = self.set_schedule(days, frequency, schedule_from, today);
+            changes.push(format!("judged {} past day{} again", judged, plural(judged)));
+        }
+        // Before the goal, so a goal given alongside a new unit lands on converted values
+        if let Some(unit) = unit {
+            let converted = match self.unit.factor_to(&unit) {
+                Some(factor) => {
+                    self.scale_values(factor);
+                    " (values converted)"
+                },
+                None => "",
+            };
+            changes.push(format!("unit: {} -> {}{}", self.unit.name(), unit.name(), converted));
+            self.unit = unit;
+        }
+        if let Some(goal) = goal {
+            let previous = self.goal_on(from);
+            self.set_goal(goal, from)?;
+            changes.push(format!("goal: {} -> {} from {}", self.unit.format(previous), self.unit.format(goal), from));
+            if edit.recalculate {
+                let changed = self.recalculate_goals(from);
+                changes.push(format!("recalculated {} logged day{}", changed, plural(changed)));
+            }
+        }
+        // After the goal, so a range is checked against the goals it will be used with
+        if let Some(direction) = direction.filter(|direction| *direction != self.direction) {
+            let previous = describe_direction(&self.direction, &self.unit);
+            let described = describe_direction(&direction, &self.unit);
+            let changed = self.set_direction(direction)?;
+            changes.push(format!("direction: {} -> {} ({} logged day{} changed status)", previous, described, changed, plural(changed)));
+        }
+        // Days, frequency and direction can change together, so they're checked against each other last
+        self.check_frequency()?;
+        Ok(changes)
+    }
+}
+
+impl UserData {
+    // Edits a habit's name, description, days, frequency, goal, unit or direction. With `dry_run` the changes are
+    // worked out and listed but not made.
+    pub fn edit_habit(&mut self, name: String, edit: &HabitEdit, dry_run: bool) -> Result<String, HabitError> {
+        self.edit_habit_as_of(name, edit, dry_run, HabitID::today())
+    }
+
+    pub fn edit_habit_as_of(&mut self, name: String, edit: &HabitEdit, dry_run: bool, today: HabitID) -> Result<String, HabitError> {
+        let mut habit = match self.data.get(&name) {
+            Some(habit) => habit.clone(),
+            None => return Err(HabitError::HabitNotFound(name)),
+        };
+
+        let mut changes = Vec::new();
+        let new_name = match &edit.rename {
+            Some(new_name) if new_name.trim().is_empty() => {
+                return Err(HabitError::InvalidArgument("the new name can't be empty".to_string()))
+            },
+            Some(new_name) if *new_name != name => {
+                if self.data.contains_key(new_name) {
+                    return Err(HabitError::HabitExists(new_name.clone()))
+                }
+                changes.push(format!("name: {} -> {}", name, new_name));
+                new_name.clone()
+            },
+            _ => name.clone(),
+        };
+        changes.extend(habit.apply_edit(edit, today)?);
+
+        if changes.is_empty() {
+            return Ok(format!("Nothing to change for {}", name))
+        }
+        let lines = changes.iter().map(|c| format!("  {}", c)).collect::<Vec<String>>().join("\n");
+        if dry_run {
+            return Ok(format!("Would change {}:\n{}", name, lines))
+        }
+
+        self.data.remove(&name);
+        self.data.insert(new_name, habit);
+        Ok(format!("Changed {}:\n{}", name, lines))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::testing::{day, habit_starting};
+    use crate::types::{NodeStatus, SCORE_STRATEGIES};
+
+    fn sample() -> UserData {
+        let mut walk = habit_starting("10-1-2023", 3000.0, Some("1-3-5"));
+        walk.description = "steps".to_string();
+        let mut user_data = UserData::new();
+        user_data.add_habit("wlak".to_string(), walk).unwrap();
+        user_data.add_habit("read".to_string(), HabitData::new("".to_string(), 1.0, None).unwrap()).unwrap();
+        user_data.edit_habit_node("wlak".to_string(), "complete", day("10-2-2023"), 0.0, false).unwrap();
+        user_data.edit_habit_node("wlak".to_string(), "complete", day("10-9-2023"), 0.0, false).unwrap();
+        user_data
+    }
+
+    #[test]
+    fn it_renames_and_edits_keeping_history() {
+        let mut user_data = sample();
+        let edit = HabitEdit {
+            rename: Some("walk".to_string()),
+            days: Some("all".to_string()),
+            goal: Some("5000".to_string()),
+            from: Some(day("10-8-2023")),
+            recalculate: true,
+            unit: Some("steps".to_string()),
+            ..Default::default()
+        };
+
+        // 10-8 and 10-10 are the Sunday and Tuesday newly scheduled before today
+        let today = day("10-12-2023");
+        let preview = user_data.edit_habit_as_of("wlak".to_string(), &edit, true, today).unwrap();
+        assert_eq!(preview, "\
+Would change wlak:
+  name: wlak -> walk
+  days: 1-3-5 -> every day from 10-8-2023
+  judged 2 past days again
+  unit: count -> steps
+  goal: 3000 steps -> 5000 steps from 10-8-2023
+  recalculated 1 logged day");
+        assert!(user_data.data.contains_key("wlak"));
+        assert!(!user_data.data.contains_key("walk"));
+
+        let message = user_data.edit_habit_as_of("wlak".to_string(), &edit, false, today).unwrap();
+        assert_eq!(message, preview.replacen("Would change", "Changed", 1));
+        let walk = user_data.data.get("walk").unwrap();
+        assert_eq!(walk.nodes.len(), 2);
+        assert_eq!(walk.nodes[&day("10-2-2023")].status, NodeStatus::COMPLETE);
+        assert_eq!(walk.nodes[&day("10-9-2023")].status, NodeStatus::PARTIAL);
+        assert!(!walk.is_scheduled(day("10-3-2023")));
+        assert!(walk.is_scheduled(day("10-10-2023")));
+        assert_eq!(walk.unit, Unit::Custom("steps".to_string()));
+        assert!(walk.check_metrics().is_ok());
+    }
+
+    #[test]
+    fn it_validates_before_changing_anything() {
+        let bad = [
+            HabitEdit { rename: Some("read".to_string()), ..Default::default() },
+            HabitEdit { rename: Some(" ".to_string()), ..Default::default() },
+            HabitEdit { description: Some("new".to_string()), days: Some("0-8".to_string()), ..Default::default() },
+            HabitEdit { goal: Some("0".to_string()), ..Default::default() },
+            HabitEdit { recalculate: true, ..Default::default() },
+            HabitEdit { from: Some(day("10-8-2023")), ..Default::default() },
+            HabitEdit { description: Some("new".to_string()), direction: Some("range".to_string()), min: Some("3000".to_string()), ..Default::default() },
+            HabitEdit { min: Some("10".to_string()), ..Default::default() },
+            HabitEdit { description: Some("new".to_string()), frequency: Some("3/week".to_string()), ..Default::default() },
+            HabitEdit { frequency: Some("often".to_string()), days: Some("all".to_string()), ..Default::default() },
+        ];
+        for edit in bad {
+            let mut user_data = sample();
+            assert!(user_data.edit_habit("wlak".to_string(), &edit, false).is_err(), "{:?}", edit);
+            assert_eq!(user_data.data["wlak"].description, "steps");
+        }
+
+        let mut user_data = sample();
+        assert_eq!(user_data.edit_habit("wlak".to_string(), &HabitEdit::default(), false).unwrap(), "Nothing to change for wlak");
+        assert_eq!(user_data.edit_habit("walk".to_string(), &HabitEdit::default(), false), Err(HabitError::HabitNotFound("walk".to_string())));
+
+        // A weekly habit can be done any day, so its weekdays have to go in the same edit
+        let edit = HabitEdit { days: Some("all".to_string()), frequency: Some("3/week".to_string()), ..Default::default() };
+        assert_eq!(user_data.edit_habit_as_of("wlak".to_string(), &edit, false, day("10-12-2023")).unwrap(), "\
+Changed wlak:
+  days: 1-3-5 -> every day from 10-12-2023
+  frequency: daily -> 3/week from 10-12-2023
+  judged 0 past days again");
+    }
+
+    #[test]
+    fn it_switches_weekly_to_daily_without_rewriting_the_past() {
+        // Twice a week from Sunday 10-1-2023, with misses failed; the week of 10-9 falls short
+        let mut gym = habit_starting("10-1-2023", 1.0, None);
+        gym.set_frequency(Frequency::parse("2/week").unwrap()).unwrap();
+        gym.set_missed_status(NodeStatus::FAILED);
+        let mut user_data = UserData::new();
+        user_data.add_habit("gym".to_string(), gym).unwrap();
+        for d in ["10-2-2023", "10-4-2023", "10-10-2023"] {
+            user_data.edit_habit_node("gym".to_string(), "complete", day(d), 0.0, false).unwrap();
+        }
+
+        let today = day("10-16-2023");
+        user_data.reconcile(today);
+        let gym = user_data.data.get("gym").unwrap();
+        let weeks = |data: &HabitData| [day("10-4-2023"), day("10-10-2023")].map(|d| data.judge_period(&data.period_holding(d, &data.stretch_holding(d)), today));
+        let (scores, streaks, judged) = (SCORE_STRATEGIES.map(|s| gym.score_as_of(s, day("10-15-2023"))), gym.streaks_as_of(day("10-15-2023")), weeks(gym));
+        assert_eq!(judged, [Some(true), Some(false)]);
+
+        let edit = HabitEdit { frequency: Some("daily".to_string()), ..Default::default() };
+        let message = user_data.edit_habit_as_of("gym".to_string(), &edit, false, today).unwrap();
+        assert!(message.ends_with("frequency: 2/week -> daily from 10-16-2023\n  judged 0 past days again"));
+
+        // No past day is filled in, and the weeks before the change score and streak as they did
+        user_data.reconcile(day("10-18-2023"));
+        let gym = user_data.data.get("gym").unwrap();
+        assert_eq!(gym.nodes.keys().filter(|d| **d < today).count(), 3);
+        assert_eq!(SCORE_STRATEGIES.map(|s| gym.score_as_of(s, day("10-15-2023"))), scores);
+        assert_eq!(gym.streaks_as_of(day("10-15-2023")), streaks);
+        assert_eq!(weeks(gym), judged);
+
+        // Days from the change on are judged one at a time
+        assert_eq!(gym.nodes[&day("10-17-2023")].status, NodeStatus::FAILED);
+    }
+}
@@ -0,0 +1,74 @@
+use std::fmt;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum HabitError {
+    HabitNotFound(String),
+    HabitExists(String),
+    NodeExists(String),
+    NoHabits,
+    InvalidDate(String),
+    InvalidEnabledDays(String),
+    UnknownCommand(String),
+    InvalidMetric(String),
+    NotScheduled(String, String),
+    StorageCorrupt(String),
+    UnsupportedFormat(u32),
+    InvalidConfig(String),
+    InvalidImport(String),
+    InvalidArgument(String),
+    Io(String),
+}
+
+impl HabitError {
+    // Exit codes the CLI returns, so scripts can tell failures apart without parsing text.
+    // 1 is shared with argh for argument errors.
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            HabitError::HabitNotFound(_) => 2,
+            HabitError::HabitExists(_) => 3,
+            HabitError::NodeExists(_) => 4,
+            HabitError::NoHabits => 5,
+            HabitError::InvalidDate(_) => 6,
+            HabitError::InvalidEnabledDays(_) => 7,
+            HabitError::UnknownCommand(_) => 8,
+            HabitError::InvalidMetric(_) => 9,
+            HabitError::StorageCorrupt(_) => 10,
+            HabitError::Io(_) => 11,
+            HabitError::NotScheduled(_, _) => 12,
+            HabitError::UnsupportedFormat(_) => 13,
+            HabitError::InvalidConfig(_) => 14,
+            HabitError::InvalidImport(_) => 15,
+            HabitError::InvalidArgument(_) => 1,
+        }
+    }
+}
+
+impl fmt::Display for HabitError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            HabitError::HabitNotFound(name) => write!(f, "No habit named '{}' exists", name),
+            HabitError::HabitExists(name) => write!(f, "A habit named '{}' already exists", name),
+            HabitError::NodeExists(date) => write!(f, "Node already exists for this habit on {}", date),
+            HabitError::NoHabits => write!(f, "No habits to list"),
+            HabitError::InvalidDate(date) => write!(f, "Invalid date '{}'", date),
+            HabitError::InvalidEnabledDays(days) => write!(f, "Invalid enabled days '{}' (expected something like 1-3-5-7)", days),
+            HabitError::UnknownCommand(cmd) => write!(f, "Unknown command '{}'", cmd),
+            HabitError::InvalidMetric(msg) => write!(f, "Status counters don't match the logged days: {}", msg),
+            HabitError::NotScheduled(habit, date) => write!(f, "{} isn't scheduled on {} (use --force to record it as a bonus day)", habit, date),
+            HabitError::StorageCorrupt(msg) => write!(f, "Stored data is corrupt: {}", msg),
+            HabitError::UnsupportedFormat(version) => write!(f, "Data file uses format version {}, which is newer than this build supports ({})", version, super::FORMAT_VERSION),
+            HabitError::InvalidConfig(msg) => write!(f, "Invalid config: {}", msg),
+            HabitError::InvalidImport(msg) => write!(f, "Invalid import: {}", msg),
+            HabitError::InvalidArgument(msg) => write!(f, "Invalid arguments: {}", msg),
+            HabitError::Io(msg) => write!(f, "I/O error: {}", msg),
+        }
+    }
+}
+
+impl std::error::Error for HabitError {}
+
+impl From<std::io::Error> for HabitError {
+    fn from(e: std::io::Error) -> Self {
+        HabitError::Io(e.to_string())
+    }
+}
@@ -0,0 +1,524 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+use chrono::{Datelike, NaiveDate};
+use serde::{Deserialize, Serialize};
+
+use super::{plural, Frequency, GoalDirection, HabitData, HabitError, HabitID, HabitNode, NodeStatus, ScheduleRevision, UserData};
+
+// Stable, human-editable form of the whole tracker used by `export` and `import`.
+// The layout is documented in the README; bump EXPORT_SCHEMA_VERSION when it changes.
+// Older schemas can still be imported as long as they are a subset of the current one.
+//
+// Schema history:
+//   1 - original layout
+//   2 - habits gain goal_history
+//   3 - habits gain unit
+//   4 - goals and values can have decimals
+//   5 - habits gain direction and min
+//   6 - habits gain frequency
+//   7 - habits gain schedule_history
+pub const EXPORT_SCHEMA_VERSION: u32 = 7;
+const OLDEST_EXPORT_SCHEMA_VERSION: u32 = 1;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ExportFormat {
+    Json,
+    Toml,
+    Csv,
+}
+
+// Narrows what `export` writes. Empty `habits` means every habit; `wide` only applies to CSV.
+#[derive(Clone, Debug, Default)]
+pub struct ExportOptions {
+    pub from: Option<HabitID>,
+    pub to: Option<HabitID>,
+    pub habits: Vec<String>,
+    pub wide: bool,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ImportMode {
+    Merge(DuplicatePolicy),
+    Replace,
+}
+
+// What a merging import does with a day that is already logged
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum DuplicatePolicy {
+    Keep,      // the existing day wins
+    Overwrite, // the imported day wins
+    Max,       // whichever has the higher value wins, the existing day on a tie
+    Error,     // refuse the import
+}
+
+impl DuplicatePolicy {
+    pub fn prefers_incoming(&self, habit: &str, day: HabitID, existing: f64, incoming: f64) -> Result<bool, HabitError> {
+        match self {
+            DuplicatePolicy::Keep => Ok(false),
+            DuplicatePolicy::Overwrite => Ok(true),
+            DuplicatePolicy::Max => Ok(incoming > existing),
+            DuplicatePolicy::Error => Err(HabitError::NodeExists(format!("{} ({})", day, habit))),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Export {
+    pub schema_version: u32,
+    #[serde(default)]
+    pub habits: Vec<ExportHabit>,
+}
+
+// Plain values come before the arrays of tables so the struct can be written as TOML
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ExportHabit {
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    pub goal: f64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub unit: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub direction: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub frequency: Option<String>,
+    pub start_date: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub enabled_days: Option<Vec<u32>>,
+    #[serde(default = "default_active")]
+    pub active: bool,
+    #[serde(default = "default_missed")]
+    pub missed: String,
+    #[serde(default)]
+    pub goal_history: Vec<ExportGoal>,
+    #[serde(default)]
+    pub schedule_history: Vec<ExportSchedule>,
+    #[serde(default)]
+    pub hidden_periods: Vec<ExportPeriod>,
+    #[serde(default)]
+    pub days: Vec<ExportDay>,
+}
+
+// A goal change, in effect from `from` until the next one
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ExportGoal {
+    pub from: String,
+    pub goal: f64,
+}
+
+// A change of weekdays or frequency, in effect from `from` until the next one
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ExportSchedule {
+    pub from: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub frequency: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub enabled_days: Option<Vec<u32>>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ExportPeriod {
+    pub from: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub to: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ExportDay {
+    pub date: String,
+    pub status: String,
+    #[serde(default)]
+    pub value: f64,
+    pub goal: f64,
+}
+
+fn default_active() -> bool {
+    true
+}
+
+fn default_missed() -> String {
+    "idle".to_string()
+}
+
+pub fn status_name(status: &NodeStatus) -> &'static str {
+    match status {
+        NodeStatus::IDLE => "idle",
+        NodeStatus::SKIPPED => "skipped",
+        NodeStatus::PARTIAL => "partial",
+        NodeStatus::FAILED => "failed",
+        NodeStatus::COMPLETE => "complete",
+    }
+}
+
+pub fn parse_status(name: &str) -> Option<NodeStatus> {
+    match name.to_lowercase().as_str() {
+        "idle" => Some(NodeStatus::IDLE),
+        "skipped" => Some(NodeStatus::SKIPPED),
+        "partial" => Some(NodeStatus::PARTIAL),
+        "failed" => Some(NodeStatus::FAILED),
+        "complete" => Some(NodeStatus::COMPLETE),
+        _ => None,
+    }
+}
+
+fn parse_iso(habit: &str, field: &str, value: &str) -> Result<HabitID, HabitError> {
+    match NaiveDate::parse_from_str(value, "%Y-%m-%d") {
+        Ok(date) => Ok(HabitID::new(date)),
+        Err(_) => Err(HabitError::InvalidImport(format!("habit '{}': {} '{}' isn't a YYYY-MM-DD date", habit, field, value))),
+    }
+}
+
+impl UserData {
+    // Names of the habits picked by `options`, sorted
+    pub fn export_names(&self, options: &ExportOptions) -> Result<Vec<&String>, HabitError> {
+        for name in options.habits.iter() {
+            if !self.data.contains_key(name) {
+                return Err(HabitError::HabitNotFound(name.clone()));
+            }
+        }
+
+        let mut names = self.data.keys()
+            .filter(|name| options.habits.is_empty() || options.habits.contains(name))
+            .collect::<Vec<&String>>();
+        names.sort();
+        Ok(names)
+    }
+
+    pub fn to_export(&self, options: &ExportOptions) -> Result<Export, HabitError> {
+        let habits = self.export_names(options)?.into_iter().map(|name| {
+            let habit = &self.data[name];
+            ExportHabit {
+                name: name.clone(),
+                description: habit.description.clone(),
+                goal: habit.goal,
+                unit: habit.unit.clone().into(),
+                direction: Some(habit.direction.name().to_string()).filter(|_| habit.direction != GoalDirection::AtLeast),
+                min: habit.direction.min(),
+                frequency: Some(habit.frequency.name()).filter(|_| !habit.frequency.is_daily()),
+                start_date: habit.start_date().iso(),
+                enabled_days: habit.enabled_days.clone(),
+                active: habit.active,
+                missed: status_name(&habit.missed_status).to_string(),
+                goal_history: habit.goal_revisions.iter().map(|(from, goal)| ExportGoal {
+                    from: from.iso(),
+                    goal: *goal,
+                }).collect(),
+                schedule_history: habit.schedule_revisions.iter().map(|revision| ExportSchedule {
+                    from: revision.from.iso(),
+                    frequency: Some(revision.frequency.name()).filter(|_| !revision.frequency.is_daily()),
+                    enabled_days: revision.enabled_days.clone(),
+                }).collect(),
+                hidden_periods: habit.inactive_periods.iter().map(|(from, to)| ExportPeriod {
+                    from: from.iso(),
+                    to: to.map(|to| to.iso()),
+                }).collect(),
+                days: habit.nodes_between(options.from, options.to).map(|(date, node)| ExportDay {
+                    date: date.iso(),
+                    status: status_name(&node.status).to_string(),
+                    value: node.value,
+                    goal: node.goal,
+                }).collect(),
+            }
+        }).collect();
+
+        Ok(Export { schema_version: EXPORT_SCHEMA_VERSION, habits })
+    }
+
+    pub fn export(&self, format: ExportFormat, options: &ExportOptions) -> Result<String, HabitError> {
+        match format {
+            ExportFormat::Json => serde_json::to_string_pretty(&self.to_export(options)?).map_err(|e| HabitError::Io(e.to_string())),
+            ExportFormat::Toml => toml::to_string(&self.to_export(options)?).map_err(|e| HabitError::Io(e.to_string())),
+            ExportFormat::Csv if options.wide => self.to_wide_csv(options),
+            ExportFormat::Csv => self.to_csv(options),
+        }
+    }
+
+    pub fn parse_export(text: &str, format: ExportFormat) -> Result<Export, HabitError> {
+        match format {
+            ExportFormat::Json => serde_json::from_str(text).map_err(|e| HabitError::InvalidImport(e.to_string())),
+            ExportFormat::Toml => toml::from_str(text).map_err(|e| HabitError::InvalidImport(e.to_string())),
+            ExportFormat::Csv => Err(HabitError::InvalidImport("CSV exports can't be imported back, use json or toml".to_string())),
+        }
+    }
+
+    // Validates the whole export before touching anything. Merge adds new habits and, for habits
+    // that already exist, adds the imported days while keeping the existing settings, settling days
+    // logged on both sides with the given policy. Replace swaps in the imported habits wholesale.
+    pub fn import(&mut self, export: Export, mode: ImportMode) -> Result<String, HabitError> {
+        if !(OLDEST_EXPORT_SCHEMA_VERSION..=EXPORT_SCHEMA_VERSION).contains(&export.schema_version) {
+            return Err(HabitError::InvalidImport(format!("unsupported schema_version {} (expected {} to {})", export.schema_version, OLDEST_EXPORT_SCHEMA_VERSION, EXPORT_SCHEMA_VERSION)));
+        }
+
+        let mut imported: HashMap<String, HabitData> = HashMap::new();
+        for habit in export.habits {
+            let name = habit.name.clone();
+            if imported.contains_key(&name) {
+                return Err(HabitError::InvalidImport(format!("habit '{}' appears more than once", name)));
+            }
+            imported.insert(name, HabitData::from_export(habit)?);
+        }
+
+        let habit_count = imported.len();
+        // Days the merge policy kept, or that already matched, aren't counted as imported
+        let day_count;
+
+        match mode {
+            ImportMode::Replace => {
+                day_count = imported.values().map(|h| h.nodes.len()).sum::<usize>();
+                self.data = imported;
+            },
+            ImportMode::Merge(policy) => {
+                // Decide every day first so a refused duplicate leaves the tracker untouched
+                let mut new_habits = Vec::new();
+                let mut new_days = Vec::new();
+                for (name, habit) in imported {
+                    match self.data.get(&name) {
+                        Some(existing) => {
+                            for (day, node) in habit.nodes {
+                                let take = match existing.nodes.get(&day) {
+                                    Some(old) if *old == node => false,
+                                    Some(old) => policy.prefers_incoming(&name, day, old.value, node.value)?,
+                                    None => true,
+                                };
+                                if take {
+                                    new_days.push((name.clone(), day, node));
+                                }
+                            }
+                        },
+                        None => new_habits.push((name, habit)),
+                    }
+                }
+
+                day_count = new_days.len() + new_habits.iter().map(|(_, h)| h.nodes.len()).sum::<usize>();
+                let mut touched = HashSet::new();
+                for (name, day, node) in new_days {
+                    self.data.get_mut(&name).unwrap().nodes.insert(day, node);
+                    touched.insert(name);
+                }
+                for name in touched {
+                    self.data.get_mut(&name).unwrap().recount_metrics();
+                }
+                self.data.extend(new_habits);
+            },
+        }
+
+        Ok(format!("Imported {} habit{} with {} day{}", habit_count, plural(habit_count), day_count, plural(day_count)))
+    }
+}
+
+impl HabitData {
+    fn from_export(habit: ExportHabit) -> Result<Self, HabitError> {
+        let name = habit.name;
+        let invalid = |msg: String| HabitError::InvalidImport(format!("habit '{}': {}", name, msg));
+
+        if name.trim().is_empty() {
+            return Err(HabitError::InvalidImport("a habit has an empty name".to_string()));
+        }
+        if !(habit.goal > 0.0 && habit.goal.is_finite()) {
+            return Err(invalid(format!("goal must be positive, got {}", habit.goal)));
+        }
+        let direction = match habit.direction.as_deref() {
+            Some(direction) => GoalDirection::from_name(direction, habit.min).map_err(|e| invalid(e.to_string()))?,
+            None if habit.min.is_some() => return Err(invalid("min needs direction = range".to_string())),
+            None => GoalDirection::AtLeast,
+        };
+        direction.check_goal(habit.goal).map_err(|e| invalid(e.to_string()))?;
+
+        let start = parse_iso(&name, "start_date", &habit.start_date)?;
+        let read_days = |days: Option<Vec<u32>>| match days {
+            Some(days) => {
+                let joined = days.iter().map(|d| d.to_string()).collect::<Vec<String>>().join("-");
+                HabitData::validate_allowed_days(joined).map(Some).map_err(|e| invalid(e.to_string()))
+            },
+            None => Ok(None),
+        };
+        let read_frequency = |frequency: Option<&str>| match frequency {
+            Some(frequency) => Frequency::parse(frequency).map_err(|e| invalid(e.to_string())),
+            None => Ok(Frequency::default()),
+        };
+        let enabled_days = read_days(habit.enabled_days)?;
+        let frequency = read_frequency(habit.frequency.as_deref())?;
+        let missed_status = match parse_status(&habit.missed) {
+            Some(status @ (NodeStatus::IDLE | NodeStatus::FAILED)) => status,
+            _ => return Err(invalid(format!("missed must be idle or failed, got '{}'", habit.missed))),
+        };
+
+        let mut inactive_periods = Vec::new();
+        for period in habit.hidden_periods {
+            let from = parse_iso(&name, "hidden_periods.from", &period.from)?;
+            let to = match period.to {
+                Some(to) => Some(parse_iso(&name, "hidden_periods.to", &to)?),
+                None => None,
+            };
+            inactive_periods.push((from, to));
+        }
+
+        let mut goal_revisions: Vec<(HabitID, f64)> = Vec::new();
+        for revision in habit.goal_history {
+            let from = parse_iso(&name, "goal_history.from", &revision.from)?;
+            if !(revision.goal > 0.0 && revision.goal.is_finite()) {
+                return Err(invalid(format!("goal_history goal must be positive, got {}", revision.goal)));
+            }
+            direction.check_goal(revision.goal).map_err(|e| invalid(e.to_string()))?;
+            if goal_revisions.last().is_some_and(|(last, _)| *last >= from) {
+                return Err(invalid("goal_history must be in date order with one change per day".to_string()));
+            }
+            goal_revisions.push((from, revision.goal));
+        }
+
+        let mut schedule_revisions: Vec<ScheduleRevision> = Vec::new();
+        for revision in habit.schedule_history {
+            let from = parse_iso(&name, "schedule_history.from", &revision.from)?;
+            if from <= start || schedule_revisions.last().is_some_and(|last| last.from >= from) {
+                return Err(invalid("schedule_history must be in date order after start_date with one change per day".to_string()));
+            }
+            schedule_revisions.push(ScheduleRevision {
+                from,
+                enabled_days: read_days(revision.enabled_days)?,
+                frequency: read_frequency(revision.frequency.as_deref())?,
+            });
+        }
+
+        let mut nodes = BTreeMap::new();
+        let mut seen = HashSet::new();
+        for day in habit.days {
+            let date = parse_iso(&name, "date", &day.date)?;
+            if !seen.insert(date) {
+                return Err(invalid(format!("{} appears more than once", day.date)));
+            }
+            let status = match parse_status(&day.status) {
+                Some(status) => status,
+                None => return Err(invalid(format!("unknown status '{}' on {}", day.status, day.date))),
+            };
+            if !(day.goal > 0.0 && day.goal.is_finite() && day.value >= 0.0 && day.value.is_finite()) {
+                return Err(invalid(format!("{} needs a positive goal and a non-negative value", day.date)));
+            }
+            nodes.insert(date, HabitNode { value: day.value, goal: day.goal, status });
+        }
+
+        let mut data = HabitData {
+            start_year: start.date().year() as u32,
+            start_month: start.date().month(),
+            start_day: start.date().day(),
+            enabled_days,
+            description: habit.description,
+            goal: habit.goal,
+            nodes,
+            metrics: HashMap::new(),
+            active: habit.active,
+            missed_status,
+            inactive_periods,
+            goal_revisions,
+            unit: habit.unit.into(),
+            direction,
+            frequency,
+            schedule_revisions,
+        };
+        data.check_frequency().map_err(|e| invalid(e.to_string()))?;
+        data.recount_metrics();
+        Ok(data)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::testing::day;
+    use crate::types::{HabitEdit, Unit};
+
+    fn sample_data() -> UserData {
+        let mut user_data = UserData::new();
+        user_data.add_habit("walk".to_string(), HabitData::new("walk steps".to_string(), 5000.0, Some("1-3-5".to_string())).unwrap()).unwrap();
+        user_data.edit_habit_node("walk".to_string(), "complete", day("10-2-2023"), 0.0, false).unwrap();
+        user_data.edit_habit_node("walk".to_string(), "set", day("10-4-2023"), 2500.0, false).unwrap();
+        user_data.edit_habit("walk".to_string(), &HabitEdit { goal: Some("6000".to_string()), from: Some(day("10-9-2023")), unit: Some("steps".to_string()), ..Default::default() }, false).unwrap();
+        user_data.add_habit("read".to_string(), HabitData::new("".to_string(), 1.0, None).unwrap()).unwrap();
+        user_data.edit_habit_node("read".to_string(), "skip", day("10-3-2023"), 0.0, false).unwrap();
+        user_data.hide_habit("read".to_string()).unwrap();
+        user_data
+    }
+
+    #[test]
+    fn it_round_trips_json_and_toml() {
+        let mut user_data = sample_data();
+        user_data.data.get_mut("walk").unwrap().set_direction(GoalDirection::Range(3000.0)).unwrap();
+        user_data.data.get_mut("read").unwrap().set_frequency(Frequency::parse("2/month").unwrap()).unwrap();
+        let weekly_from = HabitID::new(HabitID::today().date() + chrono::Duration::days(7));
+        user_data.data.get_mut("read").unwrap().set_schedule(None, Frequency::parse("weekly").unwrap(), weekly_from, HabitID::today());
+        for format in [ExportFormat::Json, ExportFormat::Toml] {
+            let text = user_data.export(format, &ExportOptions::default()).unwrap();
+            let mut imported = UserData::new();
+            imported.import(UserData::parse_export(&text, format).unwrap(), ImportMode::Replace).unwrap();
+
+            assert_eq!(imported.export(format, &ExportOptions::default()).unwrap(), text, "{:?}", format);
+            let walk = imported.data.get("walk").unwrap();
+            assert_eq!(walk.metrics.get(&NodeStatus::PARTIAL), Some(&1));
+            assert_eq!(walk.goal_on(HabitID::from_string("10-9-2023".to_string()).unwrap()), 6000.0);
+            assert_eq!(walk.unit, Unit::Custom("steps".to_string()));
+            assert_eq!(walk.direction, GoalDirection::Range(3000.0));
+            assert!(!imported.data.get("read").unwrap().active);
+            assert_eq!(imported.data.get("read").unwrap().frequency, Frequency::parse("2/month").unwrap());
+            assert_eq!(imported.data.get("read").unwrap().frequency_on(weekly_from), Frequency::parse("weekly").unwrap());
+        }
+    }
+
+    #[test]
+    fn it_merges_days_into_existing_habits() {
+        let mut user_data = sample_data();
+        let text = r#"{
+            "schema_version": 1,
+            "habits": [
+                { "name": "walk", "goal": 9999, "start_date": "2023-01-01",
+                  "days": [ { "date": "2023-10-04", "status": "complete", "value": 5000, "goal": 5000 },
+                            { "date": "2023-10-06", "status": "failed", "goal": 5000 } ] },
+                { "name": "stretch", "goal": 1, "start_date": "2023-10-01" }
+            ]
+        }"#;
+        let message = user_data.import(UserData::parse_export(text, ExportFormat::Json).unwrap(), ImportMode::Merge(DuplicatePolicy::Overwrite)).unwrap();
+        assert_eq!(message, "Imported 2 habits with 2 days");
+
+        let walk = user_data.data.get("walk").unwrap();
+        assert_eq!(walk.goal, 5000.0);
+        assert_eq!(walk.nodes.len(), 3);
+        assert_eq!(walk.metrics.get(&NodeStatus::COMPLETE), Some(&2));
+        assert!(user_data.data.contains_key("stretch"));
+        assert!(user_data.data.contains_key("read"));
+
+        // Nothing new the second time round, whether kept or already the same
+        let message = user_data.import(UserData::parse_export(text, ExportFormat::Json).unwrap(), ImportMode::Merge(DuplicatePolicy::Keep)).unwrap();
+        assert_eq!(message, "Imported 2 habits with 0 days");
+    }
+
+    #[test]
+    fn it_rejects_invalid_imports_without_changes() {
+        let bad = [
+            r#"{ "schema_version": 8, "habits": [] }"#,
+            r#"{ "schema_version": 1, "habits": [ { "name": "a", "goal": 0, "start_date": "2023-10-01" } ] }"#,
+            r#"{ "schema_version": 1, "habits": [ { "name": "a", "goal": 1, "start_date": "10-1-2023" } ] }"#,
+            r#"{ "schema_version": 1, "habits": [ { "name": "a", "goal": 1, "start_date": "2023-10-01", "enabled_days": [0] } ] }"#,
+            r#"{ "schema_version": 1, "habits": [ { "name": "a", "goal": 1, "start_date": "2023-10-01", "days": [ { "date": "2023-10-01", "status": "great", "goal": 1 } ] } ] }"#,
+            r#"{ "schema_version": 1, "habits": [ { "name": "a", "goal": 1, "start_date": "2023-10-01" }, { "name": "a", "goal": 1, "start_date": "2023-10-01" } ] }"#,
+            r#"{ "schema_version": 2, "habits": [ { "name": "a", "goal": 1, "start_date": "2023-10-01", "goal_history": [ { "from": "2023-10-05", "goal": 2 }, { "from": "2023-10-03", "goal": 3 } ] } ] }"#,
+            r#"{ "schema_version": 5, "habits": [ { "name": "a", "goal": 1, "direction": "range", "min": 2, "start_date": "2023-10-01" } ] }"#,
+            r#"{ "schema_version": 5, "habits": [ { "name": "a", "goal": 1, "min": 0.5, "start_date": "2023-10-01" } ] }"#,
+            r#"{ "schema_version": 6, "habits": [ { "name": "a", "goal": 1, "frequency": "often", "start_date": "2023-10-01" } ] }"#,
+            r#"{ "schema_version": 6, "habits": [ { "name": "a", "goal": 1, "frequency": "3/week", "start_date": "2023-10-01", "enabled_days": [1] } ] }"#,
+            r#"{ "schema_version": 7, "habits": [ { "name": "a", "goal": 1, "start_date": "2023-10-01", "schedule_history": [ { "from": "2023-10-01", "frequency": "weekly" } ] } ] }"#,
+            r#"{ "schema_version": 7, "habits": [ { "name": "a", "goal": 1, "start_date": "2023-10-01", "schedule_history": [ { "from": "2023-10-09", "frequency": "weekly" }, { "from": "2023-10-05" } ] } ] }"#,
+            r#"{ "schema_version": 7, "habits": [ { "name": "a", "goal": 1, "start_date": "2023-10-01", "schedule_history": [ { "from": "2023-10-09", "frequency": "weekly", "enabled_days": [1] } ] } ] }"#,
+        ];
+        for text in bad {
+            let mut user_data = sample_data();
+            let result = UserData::parse_export(text, ExportFormat::Json).and_then(|e| user_data.import(e, ImportMode::Replace));
+            assert!(matches!(result, Err(HabitError::InvalidImport(_))), "{}", text);
+            assert_eq!(user_data.data.len(), 2);
+        }
+    }
+}
@@ -0,0 +1,911 @@
+use std::collections::btree_map::Entry;
+use std::collections::{BTreeMap, HashMap};
+use bincode::Options;
+use chrono::{Duration, NaiveDate};
+use serde::de::{self, DeserializeOwned, Deserializer};
+use serde::Deserialize;
+
+use super::frequency::Period;
+use super::{Frequency, GoalDirection, HabitData, HabitError, HabitID, HabitNode, NodeStatus, UserData};
+
+// userdata.bin starts with MAGIC and a little-endian u32 format version, followed by the
+// bincode-encoded UserData. Files from before the header existed are versions 0 and 1.
+//
+// Version history:
+//   0 - original layout, no header
+//   1 - HabitData gains missed_status and inactive_periods, no header
+//   2 - header added, payload unchanged from 1 except that node keys are always valid dates
+//   3 - HabitData gains goal_revisions
+//   4 - HabitData gains unit
+//   5 - goals and values become f64, unit is read as a Unit (still stored as its name)
+//   6 - HabitData gains direction
+//   7 - HabitData gains frequency
+//   8 - HabitData gains schedule_revisions
+//
+// Bump FORMAT_VERSION whenever a stored struct changes, keep a copy of the old layout below, with
+// frozen copies of any live types it holds, and give it a `Layout` with a step that upgrades it.
+pub const MAGIC: &[u8; 4] = b"HBTK";
+pub const FORMAT_VERSION: u32 = 8;
+
+// The JSON and MongoDB stores name every field, so documents written since those stores were added
+// load straight into the current structs, with serde defaults filling in fields added later
+pub const OLDEST_NAMED_FORMAT_VERSION: u32 = 2;
+
+// Same encoding as bincode::serialize, but leftover bytes are an error so a damaged
+// file can't be mistaken for an older, shorter layout
+pub(super) fn options() -> impl Options {
+    bincode::DefaultOptions::new().with_fixint_encoding().reject_trailing_bytes()
+}
+
+pub(super) fn corrupt(e: bincode::Error) -> HabitError {
+    HabitError::StorageCorrupt(e.to_string())
+}
+
+impl UserData {
+    pub fn to_bytes(&self) -> Result<Vec<u8>, HabitError> {
+        let mut bytes = MAGIC.to_vec();
+        bytes.extend_from_slice(&FORMAT_VERSION.to_le_bytes());
+        bytes.extend(options().serialize(self).map_err(corrupt)?);
+        Ok(bytes)
+    }
+
+    // Also returns a note for each node of an old file that couldn't be carried over
+    pub fn from_bytes(bytes: &[u8]) -> Result<(Self, Vec<String>), HabitError> {
+        let mut notes = Vec::new();
+        let user_data = match bytes.strip_prefix(MAGIC.as_slice()) {
+            Some(rest) if rest.len() >= 4 => {
+                let version = u32::from_le_bytes([rest[0], rest[1], rest[2], rest[3]]);
+                migrate(version, &rest[4..], &mut notes)?
+            },
+            Some(_) => return Err(HabitError::StorageCorrupt("header is truncated".to_string())),
+            None => {
+                // No header: try the newest headerless layout first, since an older layout could
+                // decode a prefix of a newer file
+                match migrate(1, bytes, &mut notes) {
+                    Ok(decoded) => decoded,
+                    Err(e) => {
+                        notes.clear();
+                        migrate(0, bytes, &mut notes).map_err(|_| e)?
+                    },
+                }
+            },
+        };
+        notes.sort();
+        Ok((user_data, notes))
+    }
+}
+
+// A decoded file in one of the layouts below. `migrate` reads the layout its version was written in
+// and upgrades it a version at a time until it's the current one.
+enum Layout {
+    V2(UserDataV2),
+    V3(UserDataV3),
+    V4(UserDataV4),
+    V5(UserDataV5),
+    V6(UserDataV6),
+    V7(UserDataV7),
+    Current(UserData),
+}
+
+fn decode<T: DeserializeOwned>(payload: &[u8]) -> Result<T, HabitError> {
+    options().deserialize(payload).map_err(corrupt)
+}
+
+fn migrate(version: u32, payload: &[u8], notes: &mut Vec<String>) -> Result<UserData, HabitError> {
+    let mut layout = match version {
+        0 => Layout::V2(decode::<UserDataV0>(payload)?.upgrade(notes)),
+        1 => Layout::V2(decode::<UserDataV1>(payload)?.upgrade(notes)),
+        2 => Layout::V2(decode(payload)?),
+        3 => Layout::V3(decode(payload)?),
+        4 => Layout::V4(decode(payload)?),
+        5 => Layout::V5(decode(payload)?),
+        6 => Layout::V6(decode(payload)?),
+        7 => Layout::V7(decode(payload)?),
+        8 => Layout::Current(decode(payload)?),
+        _ => return Err(HabitError::UnsupportedFormat(version)),
+    };
+    loop {
+        layout = match layout {
+            Layout::V2(data) => Layout::V3(data.into()),
+            Layout::V3(data) => Layout::V4(data.into()),
+            Layout::V4(data) => Layout::V5(data.into()),
+            Layout::V5(data) => Layout::V6(data.into()),
+            Layout::V6(data) => Layout::V7(data.into()),
+            Layout::V7(data) => Layout::Current(data.into()),
+            Layout::Current(data) => return Ok(data),
+        }
+    }
+}
+
+// Frozen copies of the types the old layouts hold, so a change to the live types can't change how an
+// old file reads. Each is named for the last version that stored it; the live types take over from there.
+
+#[allow(clippy::upper_case_acronyms)]
+#[derive(Clone, Copy, Deserialize, PartialEq, Eq, Hash)]
+enum NodeStatusV7 {
+    IDLE,
+    SKIPPED,
+    PARTIAL,
+    FAILED,
+    COMPLETE,
+}
+
+impl From<NodeStatusV7> for NodeStatus {
+    fn from(legacy: NodeStatusV7) -> Self {
+        match legacy {
+            NodeStatusV7::IDLE => NodeStatus::IDLE,
+            NodeStatusV7::SKIPPED => NodeStatus::SKIPPED,
+            NodeStatusV7::PARTIAL => NodeStatus::PARTIAL,
+            NodeStatusV7::FAILED => NodeStatus::FAILED,
+            NodeStatusV7::COMPLETE => NodeStatus::COMPLETE,
+        }
+    }
+}
+
+fn upgrade_metrics(legacy: HashMap<NodeStatusV7, i32>) -> HashMap<NodeStatus, i32> {
+    legacy.into_iter().map(|(status, count)| (status.into(), count)).collect()
+}
+
+// A day, stored as ISO YYYY-MM-DD. Headerless files could also hold the "month-day-year" form, and
+// those years were always written out in full.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+struct DayV7(NaiveDate);
+
+impl DayV7 {
+    fn parse(s: &str) -> Option<DayV7> {
+        if let Ok(date) = NaiveDate::parse_from_str(s, "%Y-%m-%d") {
+            return Some(DayV7(date))
+        }
+        match s.split('-').map(|p| p.parse::<u32>().ok()).collect::<Vec<Option<u32>>>().as_slice() {
+            [Some(month), Some(day), Some(year @ 1000..=9999)] => NaiveDate::from_ymd_opt(*year as i32, *month, *day).map(DayV7),
+            _ => None,
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for DayV7 {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        DayV7::parse(&s).ok_or_else(|| de::Error::custom(format!("'{}' isn't a valid date", s)))
+    }
+}
+
+impl From<DayV7> for HabitID {
+    fn from(legacy: DayV7) -> Self {
+        HabitID::new(legacy.0)
+    }
+}
+
+fn upgrade_periods(legacy: Vec<(DayV7, Option<DayV7>)>) -> Vec<(HabitID, Option<HabitID>)> {
+    legacy.into_iter().map(|(from, to)| (from.into(), to.map(HabitID::from))).collect()
+}
+
+// Values and goals were whole numbers up to version 4
+#[derive(Deserialize)]
+struct HabitNodeV4 {
+    value: i32,
+    goal: i32,
+    status: NodeStatusV7,
+}
+
+impl HabitNodeV4 {
+    // Folds in another node logged for the same day. Every goal was at-least back then.
+    fn merge(&mut self, other: HabitNodeV4) {
+        self.value = self.value.saturating_add(other.value);
+        if self.value > 0 {
+            self.status = match self.value >= self.goal {
+                true => NodeStatusV7::COMPLETE,
+                false => NodeStatusV7::PARTIAL,
+            };
+        } else if self.status == NodeStatusV7::IDLE {
+            self.status = other.status;
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct HabitNodeV7 {
+    value: f64,
+    goal: f64,
+    status: NodeStatusV7,
+}
+
+impl From<HabitNodeV7> for HabitNode {
+    fn from(legacy: HabitNodeV7) -> Self {
+        HabitNode {
+            value: legacy.value,
+            goal: legacy.goal,
+            status: legacy.status.into(),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+enum GoalDirectionV7 {
+    AtLeast,
+    AtMost,
+    Exact,
+    Range(f64),
+}
+
+impl From<GoalDirectionV7> for GoalDirection {
+    fn from(legacy: GoalDirectionV7) -> Self {
+        match legacy {
+            GoalDirectionV7::AtLeast => GoalDirection::AtLeast,
+            GoalDirectionV7::AtMost => GoalDirection::AtMost,
+            GoalDirectionV7::Exact => GoalDirection::Exact,
+            GoalDirectionV7::Range(min) => GoalDirection::Range(min),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+enum PeriodV7 {
+    Days(u32),
+    Week,
+    Month,
+}
+
+#[derive(Deserialize)]
+struct FrequencyV7 {
+    times: u32,
+    period: PeriodV7,
+}
+
+impl From<FrequencyV7> for Frequency {
+    fn from(legacy: FrequencyV7) -> Self {
+        let period = match legacy.period {
+            PeriodV7::Days(days) => Period::Days(days),
+            PeriodV7::Week => Period::Week,
+            PeriodV7::Month => Period::Month,
+        };
+        Frequency { times: legacy.times, period }
+    }
+}
+
+// Parses legacy node keys into days. Keys naming the same day, ex: "10-4-2023" and "10-04-2023", are
+// merged by adding up their values, and keys that aren't dates are left out with a note. Either way
+// the stored status counters no longer match the nodes, so they're counted again.
+fn normalize_nodes(habit: &str, legacy: BTreeMap<String, HabitNodeV4>, metrics: HashMap<NodeStatusV7, i32>, notes: &mut Vec<String>)
+    -> (BTreeMap<DayV7, HabitNodeV4>, HashMap<NodeStatusV7, i32>) {
+    let count = legacy.len();
+    let mut nodes: BTreeMap<DayV7, HabitNodeV4> = BTreeMap::new();
+    for (key, node) in legacy {
+        let day = match DayV7::parse(&key) {
+            Some(day) => day,
+            None => {
+                notes.push(format!("skipped '{}' logged for '{}', it isn't a valid date", key, habit));
+                continue;
+            },
+        };
+        match nodes.entry(day) {
+            Entry::Vacant(entry) => { entry.insert(node); },
+            Entry::Occupied(mut entry) => entry.get_mut().merge(node),
+        }
+    }
+    if nodes.len() == count {
+        return (nodes, metrics)
+    }
+
+    let mut counted = HashMap::new();
+    for status in [NodeStatusV7::IDLE, NodeStatusV7::FAILED, NodeStatusV7::PARTIAL, NodeStatusV7::SKIPPED, NodeStatusV7::COMPLETE] {
+        counted.insert(status, 0);
+    }
+    for node in nodes.values() {
+        *counted.get_mut(&node.status).unwrap() += 1;
+    }
+    (nodes, counted)
+}
+
+// Headerless files took any number as a weekday. They were read as 0 = Sunday back then, so 0 becomes
+// the ISO 7, and numbers past 7, which never matched a day, are left out with a note.
+fn normalize_days(habit: &str, legacy: Option<Vec<u32>>, notes: &mut Vec<String>) -> Option<Vec<u32>> {
+    let legacy = legacy?;
+    let mut days = Vec::new();
+    for day in legacy.iter() {
+        match day {
+            0 => days.push(7),
+            1..=7 => days.push(*day),
+            _ => notes.push(format!("dropped weekday {} from '{}', weekdays are numbered 1 (Monday) to 7 (Sunday)", day, habit)),
+        }
+    }
+    days.sort();
+    days.dedup();
+    if days.is_empty() && !legacy.is_empty() {
+        notes.push(format!("'{}' had no valid weekdays left, so it's scheduled every day", habit));
+        return None
+    }
+    Some(days)
+}
+
+// Headerless files, versions 0 and 1, can predate validated dates, when nodes were keyed by whatever
+// "month-day-year" string was typed, so their keys are read as plain strings and parsed in `upgrade`
+#[derive(Deserialize)]
+struct UserDataV0 {
+    id: u32,
+    name: String,
+    data: HashMap<String, HabitDataV0>,
+}
+
+#[derive(Deserialize)]
+struct HabitDataV0 {
+    start_year: u32,
+    start_month: u32,
+    start_day: u32,
+    enabled_days: Option<Vec<u32>>,
+    description: String,
+    goal: i32,
+    nodes: BTreeMap<String, HabitNodeV4>,
+    metrics: HashMap<NodeStatusV7, i32>,
+    active: bool,
+}
+
+#[derive(Deserialize)]
+struct UserDataV1 {
+    id: u32,
+    name: String,
+    data: HashMap<String, HabitDataV1>,
+}
+
+#[derive(Deserialize)]
+struct HabitDataV1 {
+    start_year: u32,
+    start_month: u32,
+    start_day: u32,
+    enabled_days: Option<Vec<u32>>,
+    description: String,
+    goal: i32,
+    nodes: BTreeMap<String, HabitNodeV4>,
+    metrics: HashMap<NodeStatusV7, i32>,
+    active: bool,
+    missed_status: NodeStatusV7,
+    inactive_periods: Vec<(DayV7, Option<DayV7>)>,
+}
+
+#[derive(Deserialize)]
+struct UserDataV2 {
+    id: u32,
+    name: String,
+    data: HashMap<String, HabitDataV2>,
+}
+
+#[derive(Deserialize)]
+struct HabitDataV2 {
+    start_year: u32,
+    start_month: u32,
+    start_day: u32,
+    enabled_days: Option<Vec<u32>>,
+    description: String,
+    goal: i32,
+    nodes: BTreeMap<DayV7, HabitNodeV4>,
+    metrics: HashMap<NodeStatusV7, i32>,
+    active: bool,
+    missed_status: NodeStatusV7,
+    inactive_periods: Vec<(DayV7, Option<DayV7>)>,
+}
+
+impl HabitDataV2 {
+    fn start_date(&self) -> DayV7 {
+        match i32::try_from(self.start_year).ok().and_then(|year| NaiveDate::from_ymd_opt(year, self.start_month, self.start_day)) {
+            Some(date) => DayV7(date),
+            None => DayV7(chrono::Local::now().date_naive()),
+        }
+    }
+}
+
+impl UserDataV0 {
+    fn upgrade(self, notes: &mut Vec<String>) -> UserDataV2 {
+        UserDataV2 {
+            id: self.id,
+            name: self.name,
+            data: self.data.into_iter().map(|(name, habit)| {
+                let habit = habit.upgrade(&name, notes);
+                (name, habit)
+            }).collect(),
+        }
+    }
+}
+
+impl HabitDataV0 {
+    fn upgrade(self, name: &str, notes: &mut Vec<String>) -> HabitDataV2 {
+        let (nodes, metrics) = normalize_nodes(name, self.nodes, self.metrics, notes);
+        let mut data = HabitDataV2 {
+            start_year: self.start_year,
+            start_month: self.start_month,
+            start_day: self.start_day,
+            enabled_days: normalize_days(name, self.enabled_days, notes),
+            description: self.description,
+            goal: self.goal,
+            nodes,
+            metrics,
+            active: self.active,
+            missed_status: NodeStatusV7::IDLE,
+            inactive_periods: Vec::new(),
+        };
+
+        // Old files don't record when a habit was hidden, so assume it went quiet after its last logged day
+        if !data.active {
+            let hidden_since = match data.nodes.keys().next_back() {
+                Some(last) => DayV7(last.0 + Duration::days(1)),
+                None => data.start_date(),
+            };
+            data.inactive_periods.push((hidden_since, None));
+        }
+
+        data
+    }
+}
+
+impl UserDataV1 {
+    fn upgrade(self, notes: &mut Vec<String>) -> UserDataV2 {
+        UserDataV2 {
+            id: self.id,
+            name: self.name,
+            data: self.data.into_iter().map(|(name, habit)| {
+                let habit = habit.upgrade(&name, notes);
+                (name, habit)
+            }).collect(),
+        }
+    }
+}
+
+impl HabitDataV1 {
+    fn upgrade(self, name: &str, notes: &mut Vec<String>) -> HabitDataV2 {
+        let (nodes, metrics) = normalize_nodes(name, self.nodes, self.metrics, notes);
+        HabitDataV2 {
+            start_year: self.start_year,
+            start_month: self.start_month,
+            start_day: self.start_day,
+            enabled_days: normalize_days(name, self.enabled_days, notes),
+            description: self.description,
+            goal: self.goal,
+            nodes,
+            metrics,
+            active: self.active,
+            missed_status: self.missed_status,
+            inactive_periods: self.inactive_periods,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct UserDataV3 {
+    id: u32,
+    name: String,
+    data: HashMap<String, HabitDataV3>,
+}
+
+#[derive(Deserialize)]
+struct HabitDataV3 {
+    start_year: u32,
+    start_month: u32,
+    start_day: u32,
+    enabled_days: Option<Vec<u32>>,
+    description: String,
+    goal: i32,
+    nodes: BTreeMap<DayV7, HabitNodeV4>,
+    metrics: HashMap<NodeStatusV7, i32>,
+    active: bool,
+    missed_status: NodeStatusV7,
+    inactive_periods: Vec<(DayV7, Option<DayV7>)>,
+    goal_revisions: Vec<(DayV7, i32)>,
+}
+
+impl From<UserDataV2> for UserDataV3 {
+    fn from(legacy: UserDataV2) -> Self {
+        UserDataV3 {
+            id: legacy.id,
+            name: legacy.name,
+            data: legacy.data.into_iter().map(|(name, habit)| (name, habit.into())).collect(),
+        }
+    }
+}
+
+impl From<HabitDataV2> for HabitDataV3 {
+    fn from(legacy: HabitDataV2) -> Self {
+        HabitDataV3 {
+            start_year: legacy.start_year,
+            start_month: legacy.start_month,
+            start_day: legacy.start_day,
+            enabled_days: legacy.enabled_days,
+            description: legacy.description,
+            goal: legacy.goal,
+            nodes: legacy.nodes,
+            metrics: legacy.metrics,
+            active: legacy.active,
+            missed_status: legacy.missed_status,
+            inactive_periods: legacy.inactive_periods,
+            goal_revisions: Vec::new(),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct UserDataV4 {
+    id: u32,
+    name: String,
+    data: HashMap<String, HabitDataV4>,
+}
+
+#[derive(Deserialize)]
+struct HabitDataV4 {
+    start_year: u32,
+    start_month: u32,
+    start_day: u32,
+    enabled_days: Option<Vec<u32>>,
+    description: String,
+    goal: i32,
+    nodes: BTreeMap<DayV7, HabitNodeV4>,
+    metrics: HashMap<NodeStatusV7, i32>,
+    active: bool,
+    missed_status: NodeStatusV7,
+    inactive_periods: Vec<(DayV7, Option<DayV7>)>,
+    goal_revisions: Vec<(DayV7, i32)>,
+    unit: Option<String>,
+}
+
+impl From<UserDataV3> for UserDataV4 {
+    fn from(legacy: UserDataV3) -> Self {
+        UserDataV4 {
+            id: legacy.id,
+            name: legacy.name,
+            data: legacy.data.into_iter().map(|(name, habit)| (name, habit.into())).collect(),
+        }
+    }
+}
+
+impl From<HabitDataV3> for HabitDataV4 {
+    fn from(legacy: HabitDataV3) -> Self {
+        HabitDataV4 {
+            start_year: legacy.start_year,
+            start_month: legacy.start_month,
+            start_day: legacy.start_day,
+            enabled_days: legacy.enabled_days,
+            description: legacy.description,
+            goal: legacy.goal,
+            nodes: legacy.nodes,
+            metrics: legacy.metrics,
+            active: legacy.active,
+            missed_status: legacy.missed_status,
+            inactive_periods: legacy.inactive_periods,
+            goal_revisions: legacy.goal_revisions,
+            unit: None,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct UserDataV5 {
+    id: u32,
+    name: String,
+    data: HashMap<String, HabitDataV5>,
+}
+
+#[derive(Deserialize)]
+struct HabitDataV5 {
+    start_year: u32,
+    start_month: u32,
+    start_day: u32,
+    enabled_days: Option<Vec<u32>>,
+    description: String,
+    goal: f64,
+    nodes: BTreeMap<DayV7, HabitNodeV7>,
+    metrics: HashMap<NodeStatusV7, i32>,
+    active: bool,
+    missed_status: NodeStatusV7,
+    inactive_periods: Vec<(DayV7, Option<DayV7>)>,
+    goal_revisions: Vec<(DayV7, f64)>,
+    unit: Option<String>,
+}
+
+impl From<UserDataV4> for UserDataV5 {
+    fn from(legacy: UserDataV4) -> Self {
+        UserDataV5 {
+            id: legacy.id,
+            name: legacy.name,
+            data: legacy.data.into_iter().map(|(name, habit)| (name, habit.into())).collect(),
+        }
+    }
+}
+
+impl From<HabitDataV4> for HabitDataV5 {
+    fn from(legacy: HabitDataV4) -> Self {
+        HabitDataV5 {
+            start_year: legacy.start_year,
+            start_month: legacy.start_month,
+            start_day: legacy.start_day,
+            enabled_days: legacy.enabled_days,
+            description: legacy.description,
+            goal: legacy.goal as f64,
+            nodes: legacy.nodes.into_iter().map(|(day, node)| (day, HabitNodeV7 {
+                value: node.value as f64,
+                goal: node.goal as f64,
+                status: node.status,
+            })).collect(),
+            metrics: legacy.metrics,
+            active: legacy.active,
+            missed_status: legacy.missed_status,
+            inactive_periods: legacy.inactive_periods,
+            goal_revisions: legacy.goal_revisions.into_iter().map(|(day, goal)| (day, goal as f64)).collect(),
+            unit: legacy.unit,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct UserDataV6 {
+    id: u32,
+    name: String,
+    data: HashMap<String, HabitDataV6>,
+}
+
+#[derive(Deserialize)]
+struct HabitDataV6 {
+    start_year: u32,
+    start_month: u32,
+    start_day: u32,
+    enabled_days: Option<Vec<u32>>,
+    description: String,
+    goal: f64,
+    nodes: BTreeMap<DayV7, HabitNodeV7>,
+    metrics: HashMap<NodeStatusV7, i32>,
+    active: bool,
+    missed_status: NodeStatusV7,
+    inactive_periods: Vec<(DayV7, Option<DayV7>)>,
+    goal_revisions: Vec<(DayV7, f64)>,
+    unit: Option<String>,
+    direction: GoalDirectionV7,
+}
+
+impl From<UserDataV5> for UserDataV6 {
+    fn from(legacy: UserDataV5) -> Self {
+        UserDataV6 {
+            id: legacy.id,
+            name: legacy.name,
+            data: legacy.data.into_iter().map(|(name, habit)| (name, habit.into())).collect(),
+        }
+    }
+}
+
+// Every habit was at-least before directions existed
+impl From<HabitDataV5> for HabitDataV6 {
+    fn from(legacy: HabitDataV5) -> Self {
+        HabitDataV6 {
+            start_year: legacy.start_year,
+            start_month: legacy.start_month,
+            start_day: legacy.start_day,
+            enabled_days: legacy.enabled_days,
+            description: legacy.description,
+            goal: legacy.goal,
+            nodes: legacy.nodes,
+            metrics: legacy.metrics,
+            active: legacy.active,
+            missed_status: legacy.missed_status,
+            inactive_periods: legacy.inactive_periods,
+            goal_revisions: legacy.goal_revisions,
+            unit: legacy.unit,
+            direction: GoalDirectionV7::AtLeast,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct UserDataV7 {
+    id: u32,
+    name: String,
+    data: HashMap<String, HabitDataV7>,
+}
+
+#[derive(Deserialize)]
+struct HabitDataV7 {
+    start_year: u32,
+    start_month: u32,
+    start_day: u32,
+    enabled_days: Option<Vec<u32>>,
+    description: String,
+    goal: f64,
+    nodes: BTreeMap<DayV7, HabitNodeV7>,
+    metrics: HashMap<NodeStatusV7, i32>,
+    active: bool,
+    missed_status: NodeStatusV7,
+    inactive_periods: Vec<(DayV7, Option<DayV7>)>,
+    goal_revisions: Vec<(DayV7, f64)>,
+    unit: Option<String>,
+    direction: GoalDirectionV7,
+    frequency: FrequencyV7,
+}
+
+impl From<UserDataV6> for UserDataV7 {
+    fn from(legacy: UserDataV6) -> Self {
+        UserDataV7 {
+            id: legacy.id,
+            name: legacy.name,
+            data: legacy.data.into_iter().map(|(name, habit)| (name, habit.into())).collect(),
+        }
+    }
+}
+
+// Every habit was daily before frequencies existed
+impl From<HabitDataV6> for HabitDataV7 {
+    fn from(legacy: HabitDataV6) -> Self {
+        HabitDataV7 {
+            start_year: legacy.start_year,
+            start_month: legacy.start_month,
+            start_day: legacy.start_day,
+            enabled_days: legacy.enabled_days,
+            description: legacy.description,
+            goal: legacy.goal,
+            nodes: legacy.nodes,
+            metrics: legacy.metrics,
+            active: legacy.active,
+            missed_status: legacy.missed_status,
+            inactive_periods: legacy.inactive_periods,
+            goal_revisions: legacy.goal_revisions,
+            unit: legacy.unit,
+            direction: legacy.direction,
+            frequency: FrequencyV7 { times: 1, period: PeriodV7::Days(1) },
+        }
+    }
+}
+
+impl From<UserDataV7> for UserData {
+    fn from(legacy: UserDataV7) -> Self {
+        UserData {
+            id: legacy.id,
+            name: legacy.name,
+            data: legacy.data.into_iter().map(|(name, habit)| (name, habit.into())).collect(),
+        }
+    }
+}
+
+// Schedules never changed before their changes were dated
+impl From<HabitDataV7> for HabitData {
+    fn from(legacy: HabitDataV7) -> Self {
+        HabitData {
+            start_year: legacy.start_year,
+            start_month: legacy.start_month,
+            start_day: legacy.start_day,
+            enabled_days: legacy.enabled_days,
+            description: legacy.description,
+            goal: legacy.goal,
+            nodes: legacy.nodes.into_iter().map(|(day, node)| (day.into(), node.into())).collect(),
+            metrics: upgrade_metrics(legacy.metrics),
+            active: legacy.active,
+            missed_status: legacy.missed_status.into(),
+            inactive_periods: upgrade_periods(legacy.inactive_periods),
+            goal_revisions: legacy.goal_revisions.into_iter().map(|(day, goal)| (day.into(), goal)).collect(),
+            unit: legacy.unit.into(),
+            direction: legacy.direction.into(),
+            frequency: legacy.frequency.into(),
+            schedule_revisions: Vec::new(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::testing::day;
+    use serde::Serialize;
+    use crate::types::Unit;
+
+    // Each fixture holds "walk" (goal 5000, Mon/Wed/Fri, started 10-2-2023) with 10-2-2023 complete and
+    // 10-4-2023 at 2500, and "read" (goal 1, hidden) with 10-3-2023 skipped. From v3 walk's goal is 6000
+    // from 10-9-2023, from v4 its unit is steps, from v5 10-6-2023 is at 1234.5, from v6 read is at-most,
+    // v7 adds "gym" at 3/week, and from v8 walk is every day from 10-16-2023.
+    const FIXTURES: [(u32, &[u8]); 9] = [
+        (0, include_bytes!("fixtures/userdata_v0.bin")),
+        (1, include_bytes!("fixtures/userdata_v1.bin")),
+        (2, include_bytes!("fixtures/userdata_v2.bin")),
+        (3, include_bytes!("fixtures/userdata_v3.bin")),
+        (4, include_bytes!("fixtures/userdata_v4.bin")),
+        (5, include_bytes!("fixtures/userdata_v5.bin")),
+        (6, include_bytes!("fixtures/userdata_v6.bin")),
+        (7, include_bytes!("fixtures/userdata_v7.bin")),
+        (8, include_bytes!("fixtures/userdata_v8.bin")),
+    ];
+
+    #[test]
+    fn it_reads_every_fixture_version() {
+        for (version, bytes) in FIXTURES {
+            let (user_data, _) = UserData::from_bytes(bytes).unwrap_or_else(|e| panic!("v{}: {}", version, e));
+
+            let walk = user_data.data.get("walk").unwrap();
+            assert_eq!(walk.goal, 5000.0, "v{}", version);
+            assert_eq!(walk.enabled_days, Some(vec![1, 3, 5]), "v{}", version);
+            assert_eq!(walk.start_date(), day("10-2-2023"), "v{}", version);
+            assert_eq!(walk.nodes.get(&day("10-2-2023")).unwrap().status, NodeStatus::COMPLETE, "v{}", version);
+            assert_eq!(walk.nodes.get(&day("10-4-2023")).unwrap().value, 2500.0, "v{}", version);
+            assert_eq!(walk.goal_on(day("10-9-2023")), if version >= 3 { 6000.0 } else { 5000.0 }, "v{}", version);
+            assert_eq!(walk.unit, if version >= 4 { Unit::Custom("steps".to_string()) } else { Unit::Count }, "v{}", version);
+            assert_eq!(walk.nodes.get(&day("10-6-2023")).map(|n| n.value), if version >= 5 { Some(1234.5) } else { None }, "v{}", version);
+            assert!(!walk.is_scheduled(day("10-10-2023")), "v{}", version);
+            assert_eq!(walk.is_scheduled(day("10-17-2023")), version >= 8, "v{}", version);
+
+            let read = user_data.data.get("read").unwrap();
+            assert!(!read.active, "v{}", version);
+            assert_eq!(read.direction, if version >= 6 { GoalDirection::AtMost } else { GoalDirection::AtLeast }, "v{}", version);
+            assert_eq!(read.nodes.get(&day("10-3-2023")).unwrap().status, NodeStatus::SKIPPED, "v{}", version);
+            assert!(read.is_inactive(day("10-4-2023")), "v{}", version);
+            assert!(read.frequency.is_daily(), "v{}", version);
+
+            let gym = user_data.data.get("gym").map(|gym| gym.frequency);
+            assert_eq!(gym, if version >= 7 { Some(Frequency::parse("3/week").unwrap()) } else { None }, "v{}", version);
+        }
+    }
+
+    #[test]
+    fn it_round_trips_the_current_version() {
+        let user_data = UserData::from_bytes(FIXTURES[8].1).unwrap().0;
+        let bytes = user_data.to_bytes().unwrap();
+        assert!(bytes.starts_with(MAGIC));
+        assert_eq!(&bytes[4..8], &FORMAT_VERSION.to_le_bytes());
+        let decoded = UserData::from_bytes(&bytes).unwrap().0;
+        assert_eq!(decoded.data.len(), user_data.data.len());
+        for (name, habit) in user_data.data.iter() {
+            assert_eq!(decoded.data.get(name).unwrap().nodes.len(), habit.nodes.len());
+            assert_eq!(decoded.data.get(name).unwrap().inactive_periods, habit.inactive_periods);
+            assert_eq!(decoded.data.get(name).unwrap().goal_revisions, habit.goal_revisions);
+            assert_eq!(decoded.data.get(name).unwrap().unit, habit.unit);
+            assert_eq!(decoded.data.get(name).unwrap().direction, habit.direction);
+            assert_eq!(decoded.data.get(name).unwrap().frequency, habit.frequency);
+            assert_eq!(decoded.data.get(name).unwrap().schedule_revisions, habit.schedule_revisions);
+        }
+    }
+
+    #[test]
+    fn it_normalizes_legacy_node_keys() {
+        #[derive(Serialize)]
+        struct OldNode { value: i32, goal: i32, status: NodeStatus }
+        #[derive(Serialize)]
+        struct OldHabit { start_year: u32, start_month: u32, start_day: u32, enabled_days: Option<Vec<u32>>, description: String, goal: i32, nodes: BTreeMap<String, OldNode>, metrics: HashMap<NodeStatus, i32>, active: bool }
+        #[derive(Serialize)]
+        struct OldUser { id: u32, name: String, data: HashMap<String, OldHabit> }
+
+        let mut nodes = BTreeMap::new();
+        nodes.insert("10-4-2023".to_string(), OldNode { value: 2, goal: 5, status: NodeStatus::PARTIAL });
+        nodes.insert("10-04-2023".to_string(), OldNode { value: 3, goal: 5, status: NodeStatus::PARTIAL });
+        nodes.insert("10/5/2023".to_string(), OldNode { value: 5, goal: 5, status: NodeStatus::COMPLETE });
+        nodes.insert("10-6-2023".to_string(), OldNode { value: 0, goal: 5, status: NodeStatus::SKIPPED });
+        let metrics = HashMap::from([(NodeStatus::PARTIAL, 2), (NodeStatus::COMPLETE, 1), (NodeStatus::SKIPPED, 1)]);
+        let mut data = HashMap::new();
+        data.insert("walk".to_string(), OldHabit { start_year: 2023, start_month: 10, start_day: 1, enabled_days: None, description: "".to_string(), goal: 5, nodes, metrics, active: true });
+        let bytes = bincode::serialize(&OldUser { id: 1, name: "".to_string(), data }).unwrap();
+
+        let (user_data, notes) = UserData::from_bytes(&bytes).unwrap();
+        assert_eq!(notes, vec!["skipped '10/5/2023' logged for 'walk', it isn't a valid date".to_string()]);
+        let walk = user_data.data.get("walk").unwrap();
+        assert_eq!(walk.nodes.len(), 2);
+        assert_eq!(walk.nodes[&day("10-4-2023")].value, 5.0);
+        assert_eq!(walk.nodes[&day("10-4-2023")].status, NodeStatus::COMPLETE);
+        assert_eq!(walk.nodes[&day("10-6-2023")].status, NodeStatus::SKIPPED);
+        assert!(walk.check_metrics().is_ok());
+    }
+
+    #[test]
+    fn it_numbers_legacy_weekdays_from_monday() {
+        // A v0 file with "weekend" on 6-0, "typo" on 1-9 and "never" on 8 alone, all started Monday 10-2-2023,
+        // and the Sunday 10-8-2023 complete for weekend
+        let (user_data, notes) = UserData::from_bytes(include_bytes!("fixtures/userdata_v0_weekdays.bin")).unwrap();
+        assert_eq!(notes, vec![
+            "'never' had no valid weekdays left, so it's scheduled every day".to_string(),
+            "dropped weekday 8 from 'never', weekdays are numbered 1 (Monday) to 7 (Sunday)".to_string(),
+            "dropped weekday 9 from 'typo', weekdays are numbered 1 (Monday) to 7 (Sunday)".to_string(),
+        ]);
+
+        let weekend = user_data.data.get("weekend").unwrap();
+        assert_eq!(weekend.enabled_days, Some(vec![6, 7]));
+        assert!(weekend.is_scheduled(day("10-8-2023")));
+        assert_eq!(weekend.bonus_days(), 0);
+        assert_eq!(user_data.data.get("typo").unwrap().enabled_days, Some(vec![1]));
+        assert_eq!(user_data.data.get("never").unwrap().enabled_days, None);
+    }
+
+    #[test]
+    fn it_rejects_unknown_versions() {
+        let mut bytes = MAGIC.to_vec();
+        bytes.extend_from_slice(&(FORMAT_VERSION + 1).to_le_bytes());
+        assert_eq!(UserData::from_bytes(&bytes).err(), Some(HabitError::UnsupportedFormat(FORMAT_VERSION + 1)));
+        assert!(matches!(UserData::from_bytes(&MAGIC[..]), Err(HabitError::StorageCorrupt(_))));
+    }
+}
@@ -0,0 +1,341 @@
+use chrono::{Datelike, Duration, NaiveDate};
+use serde::{Deserialize, Serialize};
+
+use super::{GoalDirection, HabitData, HabitError, HabitID, NodeStatus};
+
+// The stretch of days a habit's target is counted over
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Period {
+    Days(u32), // back-to-back blocks of this many days, counted from the habit's start
+    Week,      // Monday to Sunday
+    Month,     // calendar month
+}
+
+// How often a habit should be done: `times` completed days per period. Daily habits are once per
+// day and judged day by day; anything else is judged per period, ex: 3 times per week.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Frequency {
+    pub times: u32,
+    pub period: Period,
+}
+
+pub const DAILY: Frequency = Frequency { times: 1, period: Period::Days(1) };
+
+// Longest block of days a period can cover, ex: 1/365days
+const LONGEST_DAYS: u32 = 365;
+
+impl Default for Frequency {
+    fn default() -> Self {
+        DAILY
+    }
+}
+
+// A change of weekdays or frequency, in effect from `from` until the next one
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ScheduleRevision {
+    pub from: HabitID,
+    pub enabled_days: Option<Vec<u32>>,
+    pub frequency: Frequency,
+}
+
+// Days judged with one frequency: from the habit's start, or from a change of frequency, up to the
+// day before the next change
+#[derive(Clone, Debug, PartialEq)]
+pub struct Stretch {
+    pub first: HabitID,
+    pub last: Option<HabitID>, // None while the frequency is still in effect
+    pub frequency: Frequency,
+    pub changed: bool,         // begins with a change of frequency, not the habit's start
+}
+
+// How one period went for a habit that isn't daily
+#[derive(Clone, Debug, PartialEq)]
+pub struct PeriodTally {
+    pub first: HabitID,
+    pub last: HabitID,
+    pub times: u32,      // completed days the period needs
+    pub done: u32,       // completed days
+    pub progress: f64,   // completed days plus how far partial days got
+    pub skipped: bool,   // a day in it was skipped
+    pub hidden: bool,    // the habit was hidden the whole period
+    pub cut: bool,       // a change of frequency cut it short
+}
+
+impl Frequency {
+    // Reads daily, weekly, monthly, every-other-day, or N/week, N/month and N/Kdays, ex: 3/week
+    pub fn parse(text: &str) -> Result<Frequency, HabitError> {
+        let invalid = || HabitError::InvalidArgument(format!("can't read '{}' as a frequency, expected daily, weekly, monthly, every-other-day, or N/week, N/month, N/Kdays", text));
+        let compact = text.trim().to_lowercase().replace(' ', "");
+        let (times, period) = match compact.as_str() {
+            "daily" => return Ok(DAILY),
+            "weekly" => (1, Period::Week),
+            "monthly" => (1, Period::Month),
+            "every-other-day" => (1, Period::Days(2)),
+            _ => {
+                let (times, period) = compact.split_once('/').ok_or_else(invalid)?;
+                let times = times.parse::<u32>().map_err(|_| invalid())?;
+                let period = match period {
+                    "week" => Period::Week,
+                    "month" => Period::Month,
+                    "day" => Period::Days(1),
+                    days => match days.strip_suffix("days").and_then(|d| d.parse::<u32>().ok()) {
+                        Some(days @ 1..=LONGEST_DAYS) => Period::Days(days),
+                        Some(days) if days > 0 => return Err(HabitError::InvalidArgument(format!("a period can be 1 to {} days long, not {}", LONGEST_DAYS, days))),
+                        _ => return Err(invalid()),
+                    },
+                };
+                (times, period)
+            },
+        };
+
+        let longest = match period {
+            Period::Days(days) => days,
+            Period::Week => 7,
+            Period::Month => 28,
+        };
+        if times == 0 || times > longest {
+            return Err(HabitError::InvalidArgument(format!("a habit can be done 1 to {} times {}, not {}", longest, Frequency { times: 1, period }.per_period(), times)))
+        }
+        Ok(Frequency { times, period })
+    }
+
+    // Written the way `parse` reads it, ex: 3/week
+    pub fn name(&self) -> String {
+        match self.period {
+            _ if self.is_daily() => "daily".to_string(),
+            Period::Days(days) => format!("{}/{}days", self.times, days),
+            Period::Week => format!("{}/week", self.times),
+            Period::Month => format!("{}/month", self.times),
+        }
+    }
+
+    pub fn is_daily(&self) -> bool {
+        *self == DAILY
+    }
+
+    fn per_period(&self) -> String {
+        match self.period {
+            Period::Days(1) => "per day".to_string(),
+            Period::Days(days) => format!("every {} days", days),
+            Period::Week => "per week".to_string(),
+            Period::Month => "per month".to_string(),
+        }
+    }
+
+    // ex: 3 times per week, once every 2 days
+    pub fn describe(&self) -> String {
+        match self.times {
+            1 => format!("once {}", self.per_period()),
+            times => format!("{} times {}", times, self.per_period()),
+        }
+    }
+
+    // The period holding `day`, ex: "this week" in `list`
+    pub fn this_period(&self) -> String {
+        match self.period {
+            Period::Days(1) => "today".to_string(),
+            Period::Days(days) => format!("these {} days", days),
+            Period::Week => "this week".to_string(),
+            Period::Month => "this month".to_string(),
+        }
+    }
+
+    // A number of periods, ex: 1 week, 3 months, 2 periods of 3 days
+    pub fn count(&self, count: usize) -> String {
+        let s = if count == 1 { "" } else { "s" };
+        match self.period {
+            Period::Days(1) => format!("{} day{}", count, s),
+            Period::Days(days) => format!("{} period{} of {} days", count, s, days),
+            Period::Week => format!("{} week{}", count, s),
+            Period::Month => format!("{} month{}", count, s),
+        }
+    }
+
+    // First and last day of the period holding `day`; blocks of days are counted from `start`.
+    // A period running off either end of the calendar stops there.
+    pub fn period_of(&self, day: HabitID, start: HabitID) -> (HabitID, HabitID) {
+        let date = day.date();
+        let (first, last) = match self.period {
+            Period::Days(days) => {
+                let offset = (date - start.date()).num_days().div_euclid(days as i64) * days as i64;
+                let first = start.date().checked_add_signed(Duration::days(offset)).unwrap_or(date);
+                (first, first.checked_add_signed(Duration::days(days as i64 - 1)).unwrap_or(NaiveDate::MAX))
+            },
+            Period::Week => {
+                let from_monday = date.weekday().num_days_from_monday() as i64;
+                let monday = date.checked_sub_signed(Duration::days(from_monday)).unwrap_or(NaiveDate::MIN);
+                (monday, date.checked_add_signed(Duration::days(6 - from_monday)).unwrap_or(NaiveDate::MAX))
+            },
+            Period::Month => {
+                let first = NaiveDate::from_ymd_opt(date.year(), date.month(), 1).unwrap();
+                let next = match date.month() {
+                    12 => NaiveDate::from_ymd_opt(date.year() + 1, 1, 1),
+                    month => NaiveDate::from_ymd_opt(date.year(), month + 1, 1),
+                };
+                (first, next.and_then(|next| next.pred_opt()).unwrap_or(NaiveDate::MAX))
+            },
+        };
+        (HabitID::new(first), HabitID::new(last))
+    }
+}
+
+impl HabitData {
+    // Weekly, monthly and every-N-days habits are judged per period, so they can be done on any day,
+    // and only ever count up toward their target. Checks the starting schedule and every change.
+    pub fn check_frequency(&self) -> Result<(), HabitError> {
+        let schedules = std::iter::once((&self.enabled_days, self.frequency))
+            .chain(self.schedule_revisions.iter().map(|revision| (&revision.enabled_days, revision.frequency)));
+        for (enabled_days, frequency) in schedules.filter(|(_, frequency)| !frequency.is_daily()) {
+            if enabled_days.is_some() {
+                return Err(HabitError::InvalidArgument(format!("a habit done {} can't also be limited to some weekdays", frequency.describe())))
+            }
+            if self.direction != GoalDirection::AtLeast {
+                return Err(HabitError::InvalidArgument(format!("a habit done {} can only be at-least", frequency.describe())))
+            }
+        }
+        Ok(())
+    }
+
+    pub fn set_frequency(&mut self, frequency: Frequency) -> Result<(), HabitError> {
+        let previous = self.frequency;
+        self.frequency = frequency;
+        if let Err(e) = self.check_frequency() {
+            self.frequency = previous;
+            return Err(e)
+        }
+        Ok(())
+    }
+
+    // Every stretch in date order. The first begins at the habit's start, or at its earliest logged
+    // day if that's earlier; weekday changes alone don't begin a new one.
+    pub fn stretches(&self) -> Vec<Stretch> {
+        let start = self.start_date();
+        let first = match self.nodes.keys().next() {
+            Some(first_node) if *first_node < start => *first_node,
+            _ => start,
+        };
+
+        let mut stretches = vec![Stretch { first, last: None, frequency: self.frequency, changed: false }];
+        for revision in self.schedule_revisions.iter() {
+            let current = stretches.last_mut().unwrap();
+            if revision.frequency == current.frequency {
+                continue;
+            }
+            current.last = Some(HabitID::new(revision.from.date() - Duration::days(1)));
+            stretches.push(Stretch { first: revision.from, last: None, frequency: revision.frequency, changed: true });
+        }
+        stretches
+    }
+
+    // The stretch holding `day`, the first one for days before the habit began
+    pub fn stretch_holding(&self, day: HabitID) -> Stretch {
+        let mut stretches = self.stretches();
+        let index = stretches.iter().rposition(|stretch| stretch.first <= day).unwrap_or(0);
+        stretches.swap_remove(index)
+    }
+
+    // The period of `stretch` holding `day`. Blocks of days are counted from the start of the stretch,
+    // and periods are cut short where the frequency changes.
+    pub fn period_holding(&self, day: HabitID, stretch: &Stretch) -> PeriodTally {
+        let anchor = if stretch.changed { stretch.first } else { self.start_date() };
+        let (mut first, mut last) = stretch.frequency.period_of(day, anchor);
+        let mut cut = false;
+        if stretch.changed && first < stretch.first {
+            first = stretch.first;
+            cut = true;
+        }
+        if let Some(end) = stretch.last.filter(|end| last > *end) {
+            last = end;
+            cut = true;
+        }
+        let mut tally = self.tally_period(first, last);
+        tally.times = stretch.frequency.times;
+        tally.cut = cut;
+        tally
+    }
+
+    fn tally_period(&self, first: HabitID, last: HabitID) -> PeriodTally {
+        let mut tally = PeriodTally { first, last, times: 1, done: 0, progress: 0.0, skipped: false, hidden: true, cut: false };
+        for day in first.date().iter_days().take_while(|day| *day <= last.date()) {
+            tally.hidden &= self.is_inactive(HabitID::new(day));
+        }
+        for (_, node) in self.nodes.range(first..=last) {
+            match node.status {
+                NodeStatus::COMPLETE => {
+                    tally.done += 1;
+                    tally.progress += 1.0;
+                },
+                NodeStatus::PARTIAL => tally.progress += self.direction.progress(node.value, node.goal),
+                NodeStatus::SKIPPED => tally.skipped = true,
+                _ => {},
+            }
+        }
+        tally
+    }
+
+    // Every period of `stretch` up to the one holding `today`, in order
+    pub fn periods_as_of(&self, stretch: &Stretch, today: HabitID) -> Vec<PeriodTally> {
+        let end = stretch.last.map_or(today, |last| last.min(today));
+        let mut periods = Vec::new();
+        let mut day = Some(stretch.first);
+        while let Some(first) = day.filter(|first| *first <= end) {
+            let tally = self.period_holding(first, stretch);
+            day = tally.last.date().succ_opt().map(HabitID::new);
+            periods.push(tally);
+        }
+        periods
+    }
+
+    // Whether a period counts toward scores and streaks, and if so whether its target was reached.
+    // Hidden periods are left out, as are the current period until it's done, and periods that fell
+    // short with a skipped day, that began before the habit did or that a change of frequency cut short.
+    pub fn judge_period(&self, tally: &PeriodTally, today: HabitID) -> Option<bool> {
+        let done = tally.done >= tally.times;
+        match done {
+            _ if tally.hidden => None,
+            false if tally.last >= today || tally.skipped || tally.cut || tally.first < self.start_date() => None,
+            done => Some(done),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::testing::day;
+
+    #[test]
+    fn it_reads_and_names_frequencies() {
+        assert_eq!(Frequency::parse("3/week"), Ok(Frequency { times: 3, period: Period::Week }));
+        assert_eq!(Frequency::parse("Every-Other-Day"), Ok(Frequency { times: 1, period: Period::Days(2) }));
+        assert_eq!(Frequency::parse("1/day"), Ok(DAILY));
+        for text in ["2/month", "1/3days", "daily", "weekly"] {
+            let frequency = Frequency::parse(text).unwrap();
+            assert_eq!(Frequency::parse(&frequency.name()), Ok(frequency), "{}", text);
+        }
+        for text in ["0/week", "8/week", "2/day", "3/fortnight", "often", "1/0days", "1/366days", "1/4000000000days"] {
+            assert!(matches!(Frequency::parse(text), Err(HabitError::InvalidArgument(_))), "{}", text);
+        }
+        assert_eq!(Frequency::parse("2/month").unwrap().describe(), "2 times per month");
+        assert_eq!(Frequency::parse("every-other-day").unwrap().describe(), "once every 2 days");
+    }
+
+    #[test]
+    fn it_finds_the_period_holding_a_day() {
+        // 10-4-2023 is a Wednesday
+        let start = day("10-1-2023");
+        let period = |text: &str, d: &str| Frequency::parse(text).unwrap().period_of(day(d), start);
+        assert_eq!(period("3/week", "10-4-2023"), (day("10-2-2023"), day("10-8-2023")));
+        assert_eq!(period("2/month", "12-31-2023"), (day("12-1-2023"), day("12-31-2023")));
+        assert_eq!(period("2/month", "2-10-2024"), (day("2-1-2024"), day("2-29-2024")));
+        assert_eq!(period("1/3days", "10-6-2023"), (day("10-4-2023"), day("10-6-2023")));
+        assert_eq!(period("1/3days", "9-30-2023"), (day("9-28-2023"), day("9-30-2023")));
+
+        // Periods stop at the end of the calendar
+        let last = HabitID::new(NaiveDate::MAX);
+        for text in ["1/365days", "weekly", "monthly"] {
+            assert_eq!(Frequency::parse(text).unwrap().period_of(last, start).1, last, "{}", text);
+        }
+    }
+}
@@ -0,0 +1,326 @@
+use bincode::Options;
+use chrono::{Local, TimeZone};
+use serde::{Deserialize, Serialize};
+
+use super::format::{corrupt, options};
+use super::{HabitData, HabitError, UserData, FORMAT_VERSION};
+
+// The undo journal starts with its own MAGIC and the FORMAT_VERSION its habits were encoded with,
+// followed by records, each a little-endian u32 length and the bincode-encoded JournalRecord.
+// Habits are stored in the current layout only, so a journal from an older version is started over.
+pub const JOURNAL_MAGIC: &[u8; 4] = b"HBTJ";
+
+// A habit as it was before and after one command; None where it didn't exist
+#[derive(Clone, Debug, Serialize, Deserialize)]
+struct HabitChange {
+    name: String,
+    before: Option<HabitData>,
+    after: Option<HabitData>,
+}
+
+// Every habit one command changed
+#[derive(Clone, Debug, Serialize, Deserialize)]
+struct JournalEntry {
+    time: i64,       // unix seconds
+    command: String, // as typed, ex: complete walk --date yesterday
+    changes: Vec<HabitChange>,
+}
+
+// What gets appended to the journal: a command's changes, or undo and redo moving over them
+#[derive(Clone, Debug, Serialize, Deserialize)]
+enum JournalRecord {
+    Entry(JournalEntry),
+    Undo(u32),
+    Redo(u32),
+}
+
+// Entries oldest first. The first `applied` are in effect; the rest were undone and can be redone
+// until a new command replaces them.
+#[derive(Clone, Debug, Default)]
+pub struct Journal {
+    entries: Vec<JournalEntry>,
+    applied: usize,
+    pending: Vec<JournalRecord>, // records not written yet
+    written: usize,              // records already in the file
+    rewrite: bool,               // the file can't simply be appended to
+}
+
+impl Journal {
+    // Records the habits that differ between `before` and `after`, if any
+    pub fn record(&mut self, command: &str, before: &UserData, after: &UserData) -> bool {
+        let mut names = before.data.keys().chain(after.data.keys()).collect::<Vec<&String>>();
+        names.sort();
+        names.dedup();
+
+        let changes = names.into_iter()
+            .filter(|name| before.data.get(*name) != after.data.get(*name))
+            .map(|name| HabitChange { name: name.clone(), before: before.data.get(name).cloned(), after: after.data.get(name).cloned() })
+            .collect::<Vec<HabitChange>>();
+        if changes.is_empty() {
+            return false
+        }
+        self.push(JournalRecord::Entry(JournalEntry { time: Local::now().timestamp(), command: command.to_string(), changes }));
+        true
+    }
+
+    // Puts back the habits the last `count` commands changed, newest first
+    pub fn undo(&mut self, user_data: &mut UserData, count: usize) -> Result<String, HabitError> {
+        if count == 0 {
+            return Err(HabitError::InvalidArgument("undo needs a count of at least 1".to_string()))
+        }
+        let count = count.min(self.applied);
+        if count == 0 {
+            return Ok("Nothing to undo".to_string())
+        }
+
+        let undone = &self.entries[self.applied - count..self.applied];
+        for entry in undone.iter().rev() {
+            for change in &entry.changes {
+                restore(user_data, &change.name, &change.before);
+            }
+        }
+        let lines = undone.iter().rev().map(|entry| format!("  {}", entry.command)).collect::<Vec<String>>().join("\n");
+        self.push(JournalRecord::Undo(count as u32));
+        Ok(format!("Undid:\n{}", lines))
+    }
+
+    // Makes the most recently undone command again
+    pub fn redo(&mut self, user_data: &mut UserData) -> Result<String, HabitError> {
+        let entry = match self.entries.get(self.applied) {
+            Some(entry) => entry,
+            None => return Ok("Nothing to redo".to_string()),
+        };
+        for change in &entry.changes {
+            restore(user_data, &change.name, &change.after);
+        }
+        let message = format!("Redid:\n  {}", entry.command);
+        self.push(JournalRecord::Redo(1));
+        Ok(message)
+    }
+
+    // The last `limit` commands, newest first, with the ones that were undone marked
+    pub fn log(&self, limit: usize) -> String {
+        if self.entries.is_empty() {
+            return "The journal is empty".to_string()
+        }
+        let lines = self.entries.iter().enumerate().rev().take(limit).map(|(i, entry)| {
+            let time = Local.timestamp_opt(entry.time, 0).single()
+                .map(|time| time.format("%-m-%-d-%Y %H:%M").to_string())
+                .unwrap_or_default();
+            let undone = if i >= self.applied { "  (undone)" } else { "" };
+            format!("{:>4}  {}  {}{}", i + 1, time, entry.command, undone)
+        });
+        lines.collect::<Vec<String>>().join("\n")
+    }
+
+    fn push(&mut self, record: JournalRecord) {
+        self.apply(&record);
+        self.pending.push(record);
+    }
+
+    fn apply(&mut self, record: &JournalRecord) {
+        match record {
+            JournalRecord::Entry(entry) => {
+                self.entries.truncate(self.applied);
+                self.entries.push(entry.clone());
+                self.applied = self.entries.len();
+            },
+            JournalRecord::Undo(count) => self.applied = self.applied.saturating_sub(*count as usize),
+            JournalRecord::Redo(count) => self.applied = (self.applied + *count as usize).min(self.entries.len()),
+        }
+    }
+
+    pub fn has_pending(&self) -> bool {
+        !self.pending.is_empty()
+    }
+
+    // Number of records the file would hold after the pending ones are appended
+    pub fn record_count(&self) -> usize {
+        self.written + self.pending.len()
+    }
+
+    // Whether the file has to be written from scratch instead of appended to
+    pub fn needs_rewrite(&self) -> bool {
+        self.rewrite
+    }
+
+    // Drops all but the newest `keep` entries, along with the undo and redo records, which are
+    // rewritten as a single undo of whatever is currently undone
+    pub fn compact(&mut self, keep: usize) {
+        let dropped = self.entries.len().saturating_sub(keep);
+        self.entries.drain(..dropped);
+        self.applied = self.applied.saturating_sub(dropped);
+        self.rewrite = true;
+    }
+
+    // The whole file: every entry, then one undo for those that are undone
+    pub fn to_bytes(&self) -> Result<Vec<u8>, HabitError> {
+        let mut bytes = JOURNAL_MAGIC.to_vec();
+        bytes.extend_from_slice(&FORMAT_VERSION.to_le_bytes());
+        for entry in &self.entries {
+            encode(&mut bytes, &JournalRecord::Entry(entry.clone()))?;
+        }
+        if self.applied < self.entries.len() {
+            encode(&mut bytes, &JournalRecord::Undo((self.entries.len() - self.applied) as u32))?;
+        }
+        Ok(bytes)
+    }
+
+    // Just the records not written yet, to append to the file
+    pub fn pending_bytes(&self) -> Result<Vec<u8>, HabitError> {
+        let mut bytes = Vec::new();
+        for record in &self.pending {
+            encode(&mut bytes, record)?;
+        }
+        Ok(bytes)
+    }
+
+    // Called once the pending records, or the whole journal after a rewrite, are in the file
+    pub fn mark_written(&mut self) {
+        self.written = match self.rewrite {
+            true => self.entries.len() + usize::from(self.applied < self.entries.len()),
+            false => self.written + self.pending.len(),
+        };
+        self.pending.clear();
+        self.rewrite = false;
+    }
+
+    // A record cut short by a crash, and anything after it, is dropped and the file rewritten
+    // on the next save, so the rest of the journal stays usable
+    pub fn from_bytes(bytes: &[u8]) -> Result<Journal, HabitError> {
+        let mut journal = Journal::default();
+        if bytes.is_empty() {
+            return Ok(journal)
+        }
+        let rest = match bytes.strip_prefix(JOURNAL_MAGIC.as_slice()) {
+            Some(rest) if rest.len() >= 4 => rest,
+            _ => return Err(HabitError::StorageCorrupt("not an undo journal".to_string())),
+        };
+        let version = u32::from_le_bytes([rest[0], rest[1], rest[2], rest[3]]);
+        if version > FORMAT_VERSION {
+            return Err(HabitError::UnsupportedFormat(version))
+        }
+        if version < FORMAT_VERSION {
+            journal.rewrite = true;
+            return Ok(journal)
+        }
+
+        let mut rest = &rest[4..];
+        while !rest.is_empty() {
+            let record = match rest.get(..4).map(|len| u32::from_le_bytes([len[0], len[1], len[2], len[3]]) as usize) {
+                Some(len) if rest.len() >= 4 + len => options().deserialize::<JournalRecord>(&rest[4..4 + len]).ok().map(|record| (record, len)),
+                _ => None,
+            };
+            match record {
+                Some((record, len)) => {
+                    journal.apply(&record);
+                    journal.written += 1;
+                    rest = &rest[4 + len..];
+                },
+                None => {
+                    journal.rewrite = true;
+                    break
+                },
+            }
+        }
+        Ok(journal)
+    }
+}
+
+fn encode(bytes: &mut Vec<u8>, record: &JournalRecord) -> Result<(), HabitError> {
+    let encoded = options().serialize(record).map_err(corrupt)?;
+    bytes.extend_from_slice(&(encoded.len() as u32).to_le_bytes());
+    bytes.extend(encoded);
+    Ok(())
+}
+
+fn restore(user_data: &mut UserData, name: &str, state: &Option<HabitData>) {
+    match state {
+        Some(habit) => user_data.data.insert(name.to_string(), habit.clone()),
+        None => user_data.data.remove(name),
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::testing::day;
+
+    // Runs `f` on `user_data` and records what it changed under `command`
+    fn run(journal: &mut Journal, user_data: &mut UserData, command: &str, f: impl FnOnce(&mut UserData)) {
+        let before = user_data.clone();
+        f(user_data);
+        journal.record(command, &before, user_data);
+    }
+
+    fn sample() -> (Journal, UserData) {
+        let mut journal = Journal::default();
+        let mut user_data = UserData::new();
+        run(&mut journal, &mut user_data, "add_habit walk", |u| {
+            u.add_habit("walk".to_string(), HabitData::new("".to_string(), 1.0, None).unwrap()).unwrap();
+        });
+        run(&mut journal, &mut user_data, "complete walk", |u| {
+            u.edit_habit_node("walk".to_string(), "complete", day("10-4-2023"), 0.0, true).unwrap();
+        });
+        run(&mut journal, &mut user_data, "reset_all", |u| u.clear_data());
+        (journal, user_data)
+    }
+
+    #[test]
+    fn it_undoes_and_redoes_commands() {
+        let (mut journal, mut user_data) = sample();
+        assert!(!journal.record("list", &user_data.clone(), &user_data));
+
+        assert_eq!(journal.undo(&mut user_data, 2).unwrap(), "Undid:\n  reset_all\n  complete walk");
+        assert_eq!(user_data.data["walk"].nodes.len(), 0);
+        assert_eq!(journal.redo(&mut user_data).unwrap(), "Redid:\n  complete walk");
+        assert_eq!(user_data.data["walk"].nodes.len(), 1);
+
+        // A new command drops what was left to redo
+        run(&mut journal, &mut user_data, "hide_habit walk", |u| { u.hide_habit("walk".to_string()).unwrap(); });
+        assert_eq!(journal.redo(&mut user_data).unwrap(), "Nothing to redo");
+        assert_eq!(journal.entries.len(), 3);
+
+        journal.undo(&mut user_data, 10).unwrap();
+        assert!(user_data.data.is_empty());
+        assert_eq!(journal.undo(&mut user_data, 1).unwrap(), "Nothing to undo");
+        assert!(journal.undo(&mut user_data, 0).is_err());
+    }
+
+    #[test]
+    fn it_reads_back_what_it_wrote() {
+        let (mut journal, mut user_data) = sample();
+        journal.undo(&mut user_data, 1).unwrap();
+        let mut bytes = journal.to_bytes().unwrap();
+        assert_eq!(bytes, [journal.to_bytes().unwrap()[..8].to_vec(), journal.pending_bytes().unwrap()].concat());
+
+        let mut read = Journal::from_bytes(&bytes).unwrap();
+        assert_eq!((read.entries.len(), read.applied, read.record_count()), (3, 2, 4));
+        assert!(read.log(10).starts_with("   3  "));
+        assert!(read.log(10).lines().next().unwrap().ends_with("reset_all  (undone)"));
+        assert_eq!(read.redo(&mut user_data).unwrap(), "Redid:\n  reset_all");
+
+        // A torn last record is dropped, and the file gets rewritten
+        bytes.truncate(bytes.len() - 2);
+        let read = Journal::from_bytes(&bytes).unwrap();
+        assert_eq!((read.entries.len(), read.applied), (3, 3));
+        assert!(read.needs_rewrite());
+
+        assert!(matches!(Journal::from_bytes(b"HBTK\x07\0\0\0"), Err(HabitError::StorageCorrupt(_))));
+        assert!(Journal::from_bytes(&[JOURNAL_MAGIC.as_slice(), &1u32.to_le_bytes()].concat()).unwrap().needs_rewrite());
+    }
+
+    #[test]
+    fn it_keeps_the_newest_entries_when_compacting() {
+        let (mut journal, mut user_data) = sample();
+        journal.undo(&mut user_data, 1).unwrap();
+        journal.compact(2);
+        assert_eq!((journal.entries.len(), journal.applied), (2, 1));
+        assert!(journal.log(10).contains("complete walk"));
+        assert!(!journal.log(10).contains("add_habit"));
+
+        journal.mark_written();
+        assert_eq!(journal.record_count(), 3);
+        assert_eq!(Journal::from_bytes(&journal.to_bytes().unwrap()).unwrap().applied, 1);
+    }
+}
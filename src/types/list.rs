@@ -0,0 +1,128 @@
+use super::{format_number, status_name, HabitError, HabitID, NodeStatus, Palette, UserData};
+use super::style::{pad, progress_bar, status_symbol};
+
+const BAR_WIDTH: usize = 10;
+
+impl UserData {
+    // Active habits in display order: the ones named in `order` first, in that order, then the rest by name
+    pub fn ordered_names(&self, order: &[String]) -> Vec<&String> {
+        let mut names = self.data.iter()
+            .filter(|(_, habit)| habit.active)
+            .map(|(name, _)| name)
+            .collect::<Vec<&String>>();
+        names.sort_by_key(|name| (order.iter().position(|o| o == *name).unwrap_or(order.len()), *name));
+        names
+    }
+
+    // One line per active habit that is scheduled or logged on `day`. Scheduled habits with nothing
+    // logged yet show as pending, and habits logged on an unscheduled day are marked as bonus days.
+    // Habits that aren't daily also show how many days of their period are done, ex: 2/3 this week.
+    pub fn habit_list_for_day(&self, day: HabitID, order: &[String], palette: &Palette) -> Result<String, HabitError> {
+        if self.data.is_empty() {
+            return Err(HabitError::NoHabits)
+        }
+
+        let mut rows = Vec::new();
+        for name in self.ordered_names(order) {
+            let habit = &self.data[name];
+            match habit.nodes.get(&day) {
+                Some(node) => rows.push((name, Some(&node.status), node.value, node.goal, !habit.is_scheduled(day), habit)),
+                None if habit.is_tracked(day) && day >= habit.start_date() => rows.push((name, None, 0.0, habit.goal_on(day), false, habit)),
+                None => {},
+            }
+        }
+
+        let mut out = palette.bold(&format!("Habits for {}", day));
+        if rows.is_empty() {
+            out.push_str("\nNothing scheduled");
+            return Ok(out)
+        }
+
+        let name_width = rows.iter().map(|row| row.0.chars().count()).max().unwrap_or(0);
+        for (name, status, value, goal, bonus, habit) in rows {
+            let label = status.map_or("pending", status_name);
+            let mut line = format!("{} {}  {}",
+                palette.status(status, status_symbol(status)), pad(name, name_width), palette.status(status, &pad(label, 8)));
+            if habit.is_numeric() {
+                line.push_str(&format!("  {} {}/{}", palette.status(status, &progress_bar(value, goal, BAR_WIDTH)), format_number(value), habit.unit().format(goal)));
+            }
+            let frequency = habit.frequency_on(day);
+            if !frequency.is_daily() {
+                let tally = habit.period_holding(day, &habit.stretch_holding(day));
+                let period_status = if tally.done >= tally.times { Some(&NodeStatus::COMPLETE) } else { None };
+                line.push_str(&format!("  {}", palette.status(period_status, &format!("{}/{} {}", tally.done, tally.times, frequency.this_period()))));
+            }
+            if bonus {
+                line.push_str(&format!("  {}", palette.dim("[bonus]")));
+            }
+            out.push('\n');
+            out.push_str(line.trim_end());
+        }
+        Ok(out)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::testing::{day, habit_starting};
+    use crate::types::{Frequency, HabitEdit};
+
+    #[test]
+    fn it_lists_habits_in_order_with_pending_and_bonus_days() {
+        let mut user_data = UserData::new();
+        // 10-4-2023 is a Wednesday
+        user_data.add_habit("walk".to_string(), habit_starting("10-1-2023", 5000.0, None)).unwrap();
+        user_data.add_habit("read".to_string(), habit_starting("10-1-2023", 1.0, None)).unwrap();
+        user_data.add_habit("gym".to_string(), habit_starting("10-1-2023", 1.0, Some("1-5"))).unwrap();
+        user_data.add_habit("swim".to_string(), habit_starting("10-1-2023", 1.0, Some("3"))).unwrap();
+        user_data.add_habit("old".to_string(), habit_starting("10-1-2023", 1.0, None)).unwrap();
+        user_data.hide_habit("old".to_string()).unwrap();
+
+        user_data.edit_habit_node("walk".to_string(), "set", day("10-4-2023"), 2500.0, false).unwrap();
+        user_data.edit_habit_node("read".to_string(), "complete", day("10-4-2023"), 0.0, false).unwrap();
+        user_data.edit_habit_node("gym".to_string(), "fail", day("10-4-2023"), 0.0, true).unwrap();
+
+        let list = user_data.habit_list_for_day(day("10-4-2023"), &[], &Palette::new(false)).unwrap();
+        assert_eq!(list, "\
+Habits for 10-4-2023
+✘ gym   failed    [bonus]
+✔ read  complete
+○ swim  pending
+◐ walk  partial   █████░░░░░ 2500/5000");
+
+        user_data.edit_habit("walk".to_string(), &HabitEdit { unit: Some("steps".to_string()), ..Default::default() }, false).unwrap();
+        let list = user_data.habit_list_for_day(day("10-4-2023"), &[], &Palette::new(false)).unwrap();
+        assert!(list.ends_with("◐ walk  partial   █████░░░░░ 2500/5000 steps"));
+
+        let list = user_data.habit_list_for_day(day("10-4-2023"), &["walk".to_string(), "swim".to_string()], &Palette::new(false)).unwrap();
+        let names = list.lines().skip(1).map(|line| line.split_whitespace().nth(1).unwrap()).collect::<Vec<&str>>();
+        assert_eq!(names, vec!["walk", "swim", "gym", "read"]);
+    }
+
+    #[test]
+    fn it_only_colors_when_asked() {
+        let mut user_data = UserData::new();
+        user_data.add_habit("read".to_string(), habit_starting("10-1-2023", 1.0, None)).unwrap();
+        user_data.edit_habit_node("read".to_string(), "complete", day("10-4-2023"), 0.0, false).unwrap();
+
+        assert!(!user_data.habit_list_for_day(day("10-4-2023"), &[], &Palette::new(false)).unwrap().contains('\u{1b}'));
+        assert!(user_data.habit_list_for_day(day("10-4-2023"), &[], &Palette::new(true)).unwrap().contains("\u{1b}[32m"));
+    }
+
+    #[test]
+    fn it_shows_progress_through_the_period() {
+        let mut user_data = UserData::new();
+        let mut gym = habit_starting("10-1-2023", 1.0, None);
+        gym.set_frequency(Frequency::parse("3/week").unwrap()).unwrap();
+        user_data.add_habit("gym".to_string(), gym).unwrap();
+        for d in ["10-2-2023", "10-3-2023"] {
+            user_data.edit_habit_node("gym".to_string(), "complete", day(d), 0.0, false).unwrap();
+        }
+
+        let list = user_data.habit_list_for_day(day("10-4-2023"), &[], &Palette::new(false)).unwrap();
+        assert!(list.ends_with("○ gym  pending   2/3 this week"), "{}", list);
+        let list = user_data.habit_list_for_day(day("10-9-2023"), &[], &Palette::new(false)).unwrap();
+        assert!(list.ends_with("○ gym  pending   0/3 this week"), "{}", list);
+    }
+}
@@ -1,7 +1,47 @@
-use std::{collections::HashMap, num::ParseIntError};
+use std::{collections::{BTreeMap, HashMap}, num::ParseIntError};
 use chrono::*;
 use serde::{Deserialize, Serialize};
 
+mod calendar;
+mod csv_export;
+mod csv_import;
+mod date;
+mod direction;
+mod edit;
+mod error;
+mod export;
+mod format;
+mod frequency;
+mod journal;
+mod list;
+mod streak;
+mod score;
+mod style;
+mod unit;
+mod week;
+#[cfg(test)]
+mod testing;
+pub use calendar::calendar_range;
+pub use csv_import::*;
+pub use date::*;
+pub use direction::GoalDirection;
+pub use edit::HabitEdit;
+pub use error::*;
+pub use export::*;
+pub use frequency::{Frequency, ScheduleRevision};
+pub use journal::Journal;
+pub use score::*;
+pub use style::Palette;
+pub use unit::{format_number, Unit};
+use unit::tidy;
+pub use format::{FORMAT_VERSION, OLDEST_NAMED_FORMAT_VERSION};
+
+// The ending for a counted noun, ex: 1 day, 2 days
+fn plural(count: usize) -> &'static str {
+    if count == 1 { "" } else { "s" }
+}
+
+#[allow(clippy::upper_case_acronyms)]
 #[derive(Clone, Debug, PartialEq, Serialize, Deserialize, Eq, Hash)]
 pub enum NodeStatus {
     IDLE,
@@ -12,46 +52,16 @@ pub enum NodeStatus {
 }
 
 #[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
-pub struct HabitID {
-    pub day: u32,
-    pub month: u32,
-    pub year: u32,
-}
-
-impl HabitID {
-    pub fn to_string(&self) -> String {
-        let id = format!("{}-{}-{}",
-            self.month.to_string(),
-            self.day.to_string(),
-            self.year.to_string());
-
-        return id;
-    }
-
-    pub fn from_string(val: String) -> Self {
-        let parts = val.split("-").collect::<Vec<&str>>();
-
-        let id = HabitID {
-            month: parts[0].parse::<u32>().unwrap(),
-            day: parts[1].parse::<u32>().unwrap(),
-            year: parts[2].parse::<u32>().unwrap(),
-        };
-
-        return id;
-    }
-}
-
-#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct HabitNode {
-    value: i32,
-    goal: i32,  //derived from HabitData.goal
+    value: f64,
+    goal: f64,  //derived from HabitData.goal
     status: NodeStatus
 }
 
 impl HabitNode {
     fn idle_node(&mut self) {
         self.status = NodeStatus::IDLE;
-        self.value = 0;
+        self.value = 0.0;
     }
 
     fn skip_node(&mut self) {
@@ -62,37 +72,44 @@ impl HabitNode {
         self.status = NodeStatus::FAILED;
     }
 
-    fn complete_node(&mut self) {
+    fn complete_node(&mut self, direction: &GoalDirection) {
         self.status = NodeStatus::COMPLETE;
-        self.value = self.goal;
+        self.value = direction.completed_value(self.value, self.goal);
     }
 
-    fn calculate_status(&mut self) -> NodeStatus {
-        if self.value < self.goal {
-            self.status = NodeStatus::PARTIAL;
-            return NodeStatus::PARTIAL;
-        } else {
-            self.status = NodeStatus::COMPLETE;
-            return NodeStatus::COMPLETE;
-        }
+    fn calculate_status(&mut self, direction: &GoalDirection) -> NodeStatus {
+        self.status = direction.status_for(self.value, self.goal);
+        self.status.clone()
     }
 }
 
-#[derive(Clone, Debug, Serialize, Deserialize)]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct HabitData {
     start_year: u32,
     start_month: u32,
     start_day: u32,
     enabled_days: Option<Vec<u32>>,
     description: String,
-    goal: i32, // ex: habit is walk 5000 steps per day, size would be 5000
-    nodes: HashMap<String, HabitNode>, // key is month-day-year -> oct 4 2023 = 10-4-2023
+    goal: f64, // ex: habit is walk 5000 steps per day, size would be 5000
+    nodes: BTreeMap<HabitID, HabitNode>, // keyed by calendar day, iterates in chronological order
     metrics: HashMap<NodeStatus, i32>, // number of nodes in the habit with each status
     active: bool,
+    missed_status: NodeStatus, // IDLE or FAILED, given to scheduled days that were never logged
+    inactive_periods: Vec<(HabitID, Option<HabitID>)>, // days the habit was hidden, end is None while still hidden
+    #[serde(default)]
+    goal_revisions: Vec<(HabitID, f64)>, // goal changes in date order, each in effect from its day until the next
+    #[serde(default)]
+    unit: Unit, // what goal and values are measured in
+    #[serde(default)]
+    direction: GoalDirection, // which side of the goal counts as done
+    #[serde(default)]
+    frequency: Frequency, // how many completed days per period, daily unless set
+    #[serde(default)]
+    schedule_revisions: Vec<ScheduleRevision>, // weekday and frequency changes in date order, each in effect from its day until the next
 }
 
 impl HabitData {
-    pub fn new(desc: String, goal: i32, days: Option<String>) -> Self {
+    pub fn new(desc: String, goal: f64, days: Option<String>) -> Result<Self, HabitError> {
         let today = chrono::Local::now();
         let mut fresh_metrics: HashMap<NodeStatus, i32> = HashMap::new();
         let keys = vec![NodeStatus::IDLE, NodeStatus::FAILED, NodeStatus::PARTIAL, NodeStatus::SKIPPED, NodeStatus::COMPLETE];
@@ -100,235 +117,439 @@ impl HabitData {
         for key in keys {
             fresh_metrics.insert(key, 0);
         }
-        
-        match days {
-            Some(d) => {
-                match HabitData::validate_allowed_days(d) {
-                    Ok(p) => {
-                        return HabitData {
-                            start_year: today.year() as u32,
-                            start_month: today.month(),
-                            start_day: today.day(),
-                            enabled_days: Some(p),
-                            description: desc,
-                            goal: goal,
-                            nodes: HashMap::new(),
-                            metrics: fresh_metrics,
-                            active: true,
-                        }
-                    },
-                    Err(_) => {
-                        println!("Error: Invalid enabled_days argument, so the habit has been created with all days enabled.");
-                        println!("     Delete the habit and try again, or use the edit_days command to enter a valid string (ex: '1-3-5-7') to set the days properly.");
-                        return HabitData {
-                            start_year: today.year() as u32,
-                            start_month: today.month(),
-                            start_day: today.day(),
-                            enabled_days: None,
-                            description: desc,
-                            goal: goal,
-                            nodes: HashMap::new(),
-                            metrics: fresh_metrics,
-                            active: true,
-                        }
-                    },
-                }
-            },
-            None => {
-                return HabitData { 
-                    start_year: today.year() as u32,
-                    start_month: today.month(),
-                    start_day: today.day(),
-                    enabled_days: None,
-                    description: desc,
-                    goal: goal,
-                    nodes: HashMap::new(),
-                    metrics: fresh_metrics,
-                    active: true,
-                }
+
+        let enabled_days = match days {
+            Some(d) => Some(HabitData::validate_allowed_days(d)?),
+            None => None,
+        };
+
+        Ok(HabitData {
+            start_year: today.year() as u32,
+            start_month: today.month(),
+            start_day: today.day(),
+            enabled_days,
+            description: desc,
+            goal,
+            nodes: BTreeMap::new(),
+            metrics: fresh_metrics,
+            active: true,
+            missed_status: NodeStatus::IDLE,
+            inactive_periods: Vec::new(),
+            goal_revisions: Vec::new(),
+            unit: Unit::Count,
+            direction: GoalDirection::AtLeast,
+            frequency: Frequency::default(),
+            schedule_revisions: Vec::new(),
+        })
+    }
+
+    pub fn get_current_date_id() -> HabitID {
+        HabitID::today()
+    }
+
+    pub fn start_date(&self) -> HabitID {
+        match i32::try_from(self.start_year).ok().and_then(|year| NaiveDate::from_ymd_opt(year, self.start_month, self.start_day)) {
+            Some(date) => HabitID::new(date),
+            None => HabitID::today(),
+        }
+    }
+
+    // `enabled_days` and `frequency` are the schedule the habit started with, later changes are kept in `schedule_revisions`
+    fn schedule_on(&self, day: HabitID) -> (&Option<Vec<u32>>, Frequency) {
+        match self.schedule_revisions.iter().rev().find(|revision| revision.from <= day) {
+            Some(revision) => (&revision.enabled_days, revision.frequency),
+            None => (&self.enabled_days, self.frequency),
+        }
+    }
+
+    pub fn days_on(&self, day: HabitID) -> &Option<Vec<u32>> {
+        self.schedule_on(day).0
+    }
+
+    pub fn frequency_on(&self, day: HabitID) -> Frequency {
+        self.schedule_on(day).1
+    }
+
+    // Changes the weekdays and frequency from `from` onward, replacing any change made for the same day.
+    // A change from the habit's first day or earlier replaces the schedule it started with. Returns how
+    // many days between `from` and yesterday are now judged differently. The new schedule isn't checked,
+    // so days, frequency and direction can be changed together and checked with `check_frequency` after.
+    pub fn set_schedule(&mut self, enabled_days: Option<Vec<u32>>, frequency: Frequency, from: HabitID, today: HabitID) -> usize {
+        let from = from.max(self.start_date());
+        let past = |habit: &HabitData| {
+            let mut day = from.date();
+            let mut judged = Vec::new();
+            while day < today.date() {
+                let id = HabitID::new(day);
+                day += Duration::days(1);
+                judged.push((habit.is_scheduled(id), habit.frequency_on(id)));
+            }
+            judged
+        };
+        let before = past(self);
+
+        self.schedule_revisions.retain(|revision| revision.from != from);
+        if from == self.start_date() {
+            self.enabled_days = enabled_days;
+            self.frequency = frequency;
+        } else {
+            self.schedule_revisions.push(ScheduleRevision { from, enabled_days, frequency });
+            self.schedule_revisions.sort_by_key(|revision| revision.from);
+        }
+        before.iter().zip(past(self).iter()).filter(|(before, after)| before != after).count()
+    }
+
+    // Whether the schedule in effect on `day` includes it. Weekdays are ISO numbers: 1 is Monday, 7 is Sunday.
+    pub fn is_scheduled(&self, day: HabitID) -> bool {
+        match self.days_on(day) {
+            Some(days) => days.contains(&day.date().weekday().number_from_monday()),
+            None => true,
+        }
+    }
+
+    // True on days the habit was hidden
+    pub fn is_inactive(&self, day: HabitID) -> bool {
+        self.inactive_periods.iter().any(|(from, to)| {
+            *from <= day && to.is_none_or(|to| day <= to)
+        })
+    }
+
+    // Scheduled and not hidden, so a missing node on this day means the habit was missed
+    pub fn is_tracked(&self, day: HabitID) -> bool {
+        self.is_scheduled(day) && !self.is_inactive(day)
+    }
+
+    // Logged days in date order, limited to `from..=to` when either bound is given
+    pub fn nodes_between(&self, from: Option<HabitID>, to: Option<HabitID>) -> impl Iterator<Item = (&HabitID, &HabitNode)> {
+        self.nodes.iter()
+            .skip_while(move |(day, _)| from.is_some_and(|from| **day < from))
+            .take_while(move |(day, _)| to.is_none_or(|to| **day <= to))
+    }
+
+    // `goal` is the goal the habit started with, later changes are kept in `goal_revisions`
+    pub fn goal_on(&self, day: HabitID) -> f64 {
+        match self.goal_revisions.iter().rev().find(|(from, _)| *from <= day) {
+            Some((_, goal)) => *goal,
+            None => self.goal,
+        }
+    }
+
+    // Changes the goal from `from` onward, replacing any revision made for the same day. Days already
+    // logged keep the goal they were logged against.
+    pub fn set_goal(&mut self, goal: f64, from: HabitID) -> Result<(), HabitError> {
+        if goal <= 0.0 || !goal.is_finite() {
+            return Err(HabitError::InvalidArgument(format!("goal must be positive, got {}", format_number(goal))))
+        }
+        self.direction.check_goal(goal)?;
+        self.goal_revisions.retain(|(day, _)| *day != from);
+        self.goal_revisions.push((from, goal));
+        self.goal_revisions.sort_by_key(|(day, _)| *day);
+        Ok(())
+    }
+
+    // Gives every node from `from` onward the goal in effect on its day, and works out the status
+    // again for days whose status came from their value. Returns how many days changed.
+    pub fn recalculate_goals(&mut self, from: HabitID) -> usize {
+        let goals = self.nodes.range(from..).map(|(day, _)| (*day, self.goal_on(*day))).collect::<Vec<(HabitID, f64)>>();
+
+        let mut changed = 0;
+        for (day, goal) in goals {
+            let node = self.nodes.get_mut(&day).unwrap();
+            if node.goal == goal {
+                continue;
+            }
+            let before = node.status.clone();
+            let from_value = self.direction.status_for(node.value, node.goal) == before;
+            node.goal = goal;
+            if from_value {
+                node.calculate_status(&self.direction);
+            }
+            let after = node.status.clone();
+            self.count_status_change(Some(before), Some(after));
+            changed += 1;
+        }
+        changed
+    }
+
+    // Switches to another direction and works out the status again for logged days whose status
+    // came from their value under the old one. Returns how many days changed status.
+    pub fn set_direction(&mut self, direction: GoalDirection) -> Result<usize, HabitError> {
+        let mut frequencies = std::iter::once(self.frequency).chain(self.schedule_revisions.iter().map(|revision| revision.frequency));
+        if let Some(frequency) = frequencies.find(|frequency| !frequency.is_daily()).filter(|_| direction != GoalDirection::AtLeast) {
+            return Err(HabitError::InvalidArgument(format!("a habit done {} can only be at-least", frequency.describe())))
+        }
+        direction.check_goal(self.goal)?;
+        for (_, goal) in self.goal_revisions.iter() {
+            direction.check_goal(*goal)?;
+        }
+
+        let mut changes = Vec::new();
+        for node in self.nodes.values_mut() {
+            let before = node.status.clone();
+            if self.direction.status_for(node.value, node.goal) != before {
+                continue;
+            }
+            let after = node.calculate_status(&direction);
+            if after != before {
+                changes.push((before, after));
             }
         }
+        self.direction = direction;
+
+        let changed = changes.len();
+        for (before, after) in changes {
+            self.count_status_change(Some(before), Some(after));
+        }
+        Ok(changed)
+    }
+
+    // True when values matter, not just whether the habit was done: a goal above 1 or a unit
+    pub fn is_numeric(&self) -> bool {
+        self.unit != Unit::Count || self.goal > 1.0 || self.goal_revisions.iter().any(|(_, goal)| *goal > 1.0)
+    }
+
+    pub fn unit(&self) -> &Unit {
+        &self.unit
     }
 
-    pub fn get_current_date_id() -> String {
-        let current_date = chrono::Local::now();
-        let date_id = format!("{}-{}-{}",
-            current_date.month().to_string(),
-            current_date.day().to_string(),
-            current_date.year().to_string());
-        return date_id;
+    pub fn set_unit(&mut self, unit: Unit) {
+        self.unit = unit;
     }
 
-    pub fn validate_allowed_days(s: String) -> Result<Vec<u32>, String> {
+    // Multiplies every goal and value by `factor`, used when a habit moves to another unit of the same kind
+    pub fn scale_values(&mut self, factor: f64) {
+        self.goal = tidy(self.goal * factor);
+        if let GoalDirection::Range(min) = self.direction {
+            self.direction = GoalDirection::Range(tidy(min * factor));
+        }
+        for (_, goal) in self.goal_revisions.iter_mut() {
+            *goal = tidy(*goal * factor);
+        }
+        for node in self.nodes.values_mut() {
+            node.value = tidy(node.value * factor);
+            node.goal = tidy(node.goal * factor);
+        }
+    }
+
+    pub fn set_missed_status(&mut self, status: NodeStatus) {
+        self.missed_status = status;
+    }
+
+    // Creates a node for every tracked day between the habit's start and yesterday that was never logged,
+    // going by the schedule in effect on each day. Returns how many days were filled. Habits that aren't
+    // daily can be done on any day of their period, so no single day of theirs is missed, and an unlogged
+    // day of an at-most habit stayed under the limit, so it's never filled in as failed.
+    pub fn fill_missed_days(&mut self, today: HabitID) -> usize {
+        let mut filled = 0;
+        let mut day = self.start_date().date();
+
+        while day < today.date() {
+            let id = HabitID::new(day);
+            day += Duration::days(1);
+
+            if !self.is_tracked(id) || !self.frequency_on(id).is_daily() || self.nodes.contains_key(&id) {
+                continue;
+            }
+
+            let _ = self.insert_fresh_node(id);
+            if self.missed_status == NodeStatus::FAILED && !self.direction.unlogged_is_done() {
+                let _ = self.edit_node(id, "fail", 0.0);
+            }
+            filled += 1;
+        }
+        filled
+    }
+
+    // Parses enabled days written as ISO weekday numbers joined by dashes, ex: 1-3-5 for Mon/Wed/Fri
+    pub fn validate_allowed_days(s: String) -> Result<Vec<u32>, HabitError> {
         for x in s.split("-").map(|s| s.parse::<u32>()).collect::<Vec<Result<u32, ParseIntError>>>().iter() {
             match x {
-                Ok(_) => {},
-                Err(_) => {
-                    return Err("Error: allowed_days argument was an invalid string.".to_string())
+                Ok(1..=7) => {},
+                _ => {
+                    return Err(HabitError::InvalidEnabledDays(s))
                 },
             }
         };
-        
-        let parts = s.split("-").map(|s| s.parse::<u32>().unwrap()).collect::<Vec<u32>>();
-        return Ok(parts);
+
+        let mut parts = s.split("-").map(|s| s.parse::<u32>().unwrap()).collect::<Vec<u32>>();
+        parts.sort();
+        parts.dedup();
+        Ok(parts)
     }
 
-    pub fn insert_fresh_node(&mut self, date: String) -> Result<String, String> {
+    pub fn insert_fresh_node(&mut self, date: HabitID) -> Result<HabitID, HabitError> {
         let exists = self.nodes.get(&date);
         match exists {
             Some(_) => {
-                Err("Node already exists for this habit on the specified date!".to_string())
+                Err(HabitError::NodeExists(date.to_string()))
             },
             None => {
-                self.nodes.insert(date.clone(), self.create_node_from_habit());
-                let _= self.shift_metric(None, Some(NodeStatus::IDLE));
+                self.nodes.insert(date, self.create_node_from_habit(date));
+                self.count_status_change(None, Some(NodeStatus::IDLE));
                 Ok(date)
             }
         }
     }
 
-    pub fn create_node_from_habit(&self) -> HabitNode {
+    pub fn create_node_from_habit(&self, day: HabitID) -> HabitNode {
         HabitNode {
-            value: 0,
-            goal: self.goal,
+            value: 0.0,
+            goal: self.goal_on(day),
             status: NodeStatus::IDLE,
         }
     }
 
-    pub fn edit_node(&mut self, day: String, command: &str, value: i32) -> Result<String, String> {
-        match self.nodes.get_mut(&day) {
-            Some(node) => {
-                let current_status = node.status.clone();
-                match command {
-                    "complete" => {
-                        node.complete_node();
-                        let _ = self.shift_metric(Some(current_status), Some(NodeStatus::COMPLETE));
-                        Ok("".to_string())
-                    },
-                    "fail" => {
-                        node.fail_node();
-                        let _ = self.shift_metric(Some(current_status), Some(NodeStatus::FAILED));
-                        Ok("".to_string())
-                    },
-                    "set" => {
-                        node.value = value;
-                        let new_status = node.calculate_status();
-                        if new_status != current_status {
-                            let _ = self.shift_metric(Some(current_status), Some(new_status));
-                        }
-                        Ok("".to_string())
-                    },
-                    "skip" => {
-                        node.skip_node();
-                        let _ = self.shift_metric(Some(current_status), Some(NodeStatus::SKIPPED));
-                        Ok("".to_string())
-                    },
-                    "reset" => {
-                        node.idle_node();
-                        let _ = self.shift_metric(Some(current_status), Some(NodeStatus::IDLE));
-                        Ok("".to_string())
-                    },
-                    "increment" => {
-                        node.value += value;
-                        let new_status = node.calculate_status();
-                        if new_status != current_status {
-                            let _ = self.shift_metric(Some(current_status), Some(new_status));
-                        }
-                        Ok("".to_string())
-                    },
-                    _ => {
-                        Err("Incorrect input to edit_node()".to_string())
-                    }
-                }
+    pub fn edit_node(&mut self, day: HabitID, command: &str, value: f64) -> Result<String, HabitError> {
+        if !["complete", "fail", "set", "skip", "reset", "increment"].contains(&command) {
+            return Err(HabitError::UnknownCommand(command.to_string()))
+        }
+
+        // A sum too large to store would turn every score and total it's part of into inf or NaN
+        let current = self.nodes.get(&day).map_or(0.0, |node| node.value);
+        let total = if command == "increment" { current + value } else { value };
+        if ["set", "increment"].contains(&command) && !total.is_finite() {
+            return Err(HabitError::InvalidArgument(format!("the amount logged on {} would be too large to store", day)))
+        }
+
+        let before = self.nodes.get(&day).map(|node| node.status.clone());
+        let fresh = self.create_node_from_habit(day);
+        let node = self.nodes.entry(day).or_insert(fresh);
+        match command {
+            "complete" => {
+                node.complete_node(&self.direction);
             },
-            None => {
-                match self.insert_fresh_node(day.clone()) {
-                    Ok(_) => {
-                        let node = self.nodes.get_mut(&day).unwrap();
-                        match command {
-                            "complete" => {
-                                node.complete_node();
-                                let _ = self.shift_metric(Some(NodeStatus::IDLE), Some(NodeStatus::COMPLETE));
-                                Ok("".to_string())
-                            },
-                            "fail" => {
-                                node.fail_node();
-                                let _ = self.shift_metric(Some(NodeStatus::IDLE), Some(NodeStatus::FAILED));
-                                Ok("".to_string())
-                            },
-                            "set" => {
-                                node.value = value;
-                                let new_status = node.calculate_status();
-                                if new_status != NodeStatus::IDLE {
-                                    let _ = self.shift_metric(Some(NodeStatus::IDLE), Some(new_status));
-                                }
-                                Ok("".to_string())
-                            },
-                            "skip" => {
-                                node.skip_node();
-                                let _ = self.shift_metric(Some(NodeStatus::IDLE), Some(NodeStatus::SKIPPED));
-                                Ok("".to_string())
-                            },
-                            "reset" => {
-                                node.idle_node();
-                                Ok("".to_string())
-                            },
-                            "increment" => {
-                                node.value += value;
-                                let new_status = node.calculate_status();
-                                if new_status != NodeStatus::IDLE {
-                                    let _ = self.shift_metric(Some(NodeStatus::IDLE), Some(new_status));
-                                }
-                                Ok("".to_string())
-                            },
-                            _ => {
-                                Err("Incorrect input to edit_node()".to_string())
-                            }
-                        }
-                    },
-                    Err(e) => {
-                        println!("{}", e);
-                        Err(e)
-                    },
-                }
+            "fail" => {
+                node.fail_node();
+            },
+            "set" => {
+                node.value = tidy(value);
+                node.calculate_status(&self.direction);
+            },
+            "skip" => {
+                node.skip_node();
+            },
+            "reset" => {
+                node.idle_node();
+            },
+            _ => {
+                node.value = tidy(node.value + value);
+                node.calculate_status(&self.direction);
             },
         }
+
+        let after = node.status.clone();
+        self.count_status_change(before, Some(after));
+        Ok("".to_string())
     }
 
-    pub fn shift_metric(&mut self, decrement: Option<NodeStatus>, increment: Option<NodeStatus>) -> Result<String, String> {
-        match increment {
-            Some(inc_status) => {
-                if let Some(dec_status) = decrement {
-                    *self.metrics.get_mut(&dec_status).unwrap() -= 1;
-                    *self.metrics.get_mut(&inc_status).unwrap() += 1;
-                    Ok("".to_string())
-                } else {
-                    *self.metrics.get_mut(&inc_status).unwrap() += 1;
-                    Ok("".to_string())
-                }
-            },
-            None => {
-                Err("Invalid metric increment argument.".to_string())
-            },
+    // Keeps `metrics` in step with a node changing from one status to another (None for no node).
+    // Every change to a node's status goes through here, so the counters can't drift.
+    fn count_status_change(&mut self, before: Option<NodeStatus>, after: Option<NodeStatus>) {
+        if let Some(status) = before {
+            *self.metrics.entry(status).or_insert(0) -= 1;
+        }
+        if let Some(status) = after {
+            *self.metrics.entry(status).or_insert(0) += 1;
+        }
+    }
+
+    // Per-status node counts taken from the nodes themselves
+    pub fn counted_metrics(&self) -> HashMap<NodeStatus, i32> {
+        let mut counts = HashMap::new();
+        for key in [NodeStatus::IDLE, NodeStatus::FAILED, NodeStatus::PARTIAL, NodeStatus::SKIPPED, NodeStatus::COMPLETE] {
+            counts.insert(key, 0);
+        }
+        for node in self.nodes.values() {
+            *counts.get_mut(&node.status).unwrap() += 1;
+        }
+        counts
+    }
+
+    // Compares the stored counters with the nodes, naming every status that doesn't match
+    pub fn check_metrics(&self) -> Result<(), HabitError> {
+        let counted = self.counted_metrics();
+        let mut mismatches = Vec::new();
+        for status in [NodeStatus::IDLE, NodeStatus::FAILED, NodeStatus::PARTIAL, NodeStatus::SKIPPED, NodeStatus::COMPLETE] {
+            let stored = *self.metrics.get(&status).unwrap_or(&0);
+            if stored != counted[&status] {
+                mismatches.push(format!("{:?} stored {}, counted {}", status, stored, counted[&status]));
+            }
+        }
+        match mismatches.is_empty() {
+            true => Ok(()),
+            false => Err(HabitError::InvalidMetric(mismatches.join(", "))),
+        }
+    }
+
+    // Rebuilds the status counters from the nodes, used after nodes are replaced in bulk
+    pub fn recount_metrics(&mut self) {
+        self.metrics = self.counted_metrics();
+    }
+
+    // Per-status node counts, limited to days the habit is scheduled on
+    pub fn scheduled_counts(&self) -> HashMap<NodeStatus, i32> {
+        let mut counts: HashMap<NodeStatus, i32> = HashMap::new();
+        for (day, node) in self.nodes.iter() {
+            if self.is_scheduled(*day) {
+                *counts.entry(node.status.clone()).or_insert(0) += 1;
+            }
         }
+        counts
     }
 
-    pub fn print_metrics(&self) {
-        let partial = *self.metrics.get(&NodeStatus::PARTIAL).unwrap() as f64 / (2*self.nodes.len()) as f64;
-        let complete = *self.metrics.get(&NodeStatus::COMPLETE).unwrap() as f64 / self.nodes.len() as f64;
-        let overall = (complete + partial) * 100 as f64;
-        let overall_count = *self.metrics.get(&NodeStatus::COMPLETE).unwrap() as f64 + (*self.metrics.get(&NodeStatus::PARTIAL).unwrap() as f64/2 as f64);
-        println!("Overall habit score: {:.1}% ({:.1}/{:?})", overall, overall_count, self.nodes.len() as i32);
-        println!("Number of completed days: {:?}", self.metrics.get(&NodeStatus::COMPLETE).unwrap());
-        println!("Number of partially completed days: {:?}", self.metrics.get(&NodeStatus::PARTIAL).unwrap());
-        println!("Number of skipped days: {:?}", self.metrics.get(&NodeStatus::SKIPPED).unwrap());
-        println!("Number of idle days: {:?}", self.metrics.get(&NodeStatus::IDLE).unwrap());
-        println!("Number of failed days: {:?}", self.metrics.get(&NodeStatus::FAILED).unwrap());
+    // Days logged with some amount while unscheduled; filled-in misses and plain fails don't count
+    pub fn bonus_days(&self) -> usize {
+        self.nodes.iter().filter(|(day, node)| !self.is_scheduled(**day) && node.value > 0.0).count()
+    }
+
+    pub fn metrics_summary(&self, strategies: &[ScoreStrategy]) -> String {
+        let mut lines = Vec::new();
+        let counts = self.scheduled_counts();
+        let count = |status: NodeStatus| *counts.get(&status).unwrap_or(&0);
+
+        let today = HabitID::today();
+        let frequency = self.frequency_on(today);
+        lines.push(format!("Goal: {} per day", self.direction.describe(self.goal_on(today), &self.unit)));
+        if !frequency.is_daily() {
+            lines.push(format!("Frequency: {}", frequency.describe()));
+        }
+        if self.is_numeric() {
+            lines.push(format!("Logged in total: {}", self.unit.format(self.nodes.values().map(|node| node.value).sum())));
+        }
+
+        // Days and periods are scored alike, so a habit whose frequency changed is scored over both
+        let mixed = self.stretches().iter().filter(|stretch| stretch.first <= today).count() > 1;
+        for strategy in strategies {
+            match self.score(*strategy) {
+                Some(score) if mixed => lines.push(format!("Habit score ({}): {:.1}% over {} days and periods", strategy.name(), score.value * 100.0, score.days)),
+                Some(score) => lines.push(format!("Habit score ({}): {:.1}% over {}", strategy.name(), score.value * 100.0, frequency.count(score.days))),
+                None => lines.push(format!("Habit score ({}): no scored days yet", strategy.name())),
+            }
+        }
+        lines.push(format!("Number of completed days: {:?}", count(NodeStatus::COMPLETE)));
+        lines.push(format!("Number of partially completed days: {:?}", count(NodeStatus::PARTIAL)));
+        lines.push(format!("Number of skipped days: {:?}", count(NodeStatus::SKIPPED)));
+        lines.push(format!("Number of idle days: {:?}", count(NodeStatus::IDLE)));
+        lines.push(format!("Number of failed days: {:?}", count(NodeStatus::FAILED)));
+        lines.push(format!("Number of bonus days (logged on unscheduled days): {:?}", self.bonus_days()));
+        if !self.goal_revisions.is_empty() {
+            let changes = self.goal_revisions.iter().map(|(from, goal)| format!("{} from {}", self.unit.format(*goal), from)).collect::<Vec<String>>();
+            lines.push(format!("Goal history: {} at the start, {}", self.unit.format(self.goal), changes.join(", ")));
+        }
+        if !self.schedule_revisions.is_empty() {
+            let describe = |days: &Option<Vec<u32>>, frequency: Frequency| match frequency.is_daily() {
+                true => edit::describe_days(days),
+                false => frequency.name(),
+            };
+            let changes = self.schedule_revisions.iter().map(|revision| format!("{} from {}", describe(&revision.enabled_days, revision.frequency), revision.from)).collect::<Vec<String>>();
+            lines.push(format!("Schedule history: {} at the start, {}", describe(&self.enabled_days, self.frequency), changes.join(", ")));
+        }
+        lines.join("\n")
     }
 }
 
 
-#[derive(Clone, Debug, Serialize, Deserialize)]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct UserData {
     id: u32,
     name: String,
@@ -340,26 +561,61 @@ impl UserData {
         UserData { id: 0, name: "".to_string(), data: HashMap::new() }
     }
 
+    // Fills in missed days for every habit, run each time the data is loaded
+    pub fn reconcile(&mut self, today: HabitID) {
+        for habit in self.data.values_mut() {
+            habit.fill_missed_days(today);
+        }
+    }
+
     pub fn clear_data(&mut self) {
         self.data = HashMap::new();
     }
 
-    pub fn show_history(&self, habit: String) -> Result<String, String> {
+    // The score for `strategy`, or for every strategy when it's None, then counts and streaks
+    pub fn show_history(&self, habit: String, strategy: Option<ScoreStrategy>) -> Result<String, HabitError> {
         match self.data.get(&habit) {
             Some(data) => {
-                data.print_metrics();
-                Ok("".to_string())
+                let metrics = match strategy {
+                    Some(strategy) => data.metrics_summary(&[strategy]),
+                    None => data.metrics_summary(&SCORE_STRATEGIES),
+                };
+                Ok(format!("{}\n{}", metrics, data.streak_summary()))
             },
             None => {
-                Err("Couldn't find specified habit!".to_string())
+                Err(HabitError::HabitNotFound(habit))
             },
         }
     }
 
-    pub fn add_habit(&mut self, name: String, data: HabitData) -> Result<String, String> {
+    // Finds habits whose stored status counters don't match their nodes and, unless `check_only`,
+    // rebuilds them. With `check_only` a mismatch is an error, so scripts can tell from the exit code.
+    pub fn doctor(&mut self, check_only: bool) -> Result<String, HabitError> {
+        let mut names = self.data.keys().cloned().collect::<Vec<String>>();
+        names.sort();
+
+        let mut problems = Vec::new();
+        for name in names {
+            let habit = self.data.get_mut(&name).unwrap();
+            if let Err(HabitError::InvalidMetric(msg)) = habit.check_metrics() {
+                problems.push(format!("{}: {}", name, msg));
+                if !check_only {
+                    habit.recount_metrics();
+                }
+            }
+        }
+
+        match (problems.is_empty(), check_only) {
+            (true, _) => Ok("All status counters match the logged days".to_string()),
+            (false, true) => Err(HabitError::InvalidMetric(problems.join("; "))),
+            (false, false) => Ok(format!("{}\nRepaired {} habit{}", problems.join("\n"), problems.len(), plural(problems.len()))),
+        }
+    }
+
+    pub fn add_habit(&mut self, name: String, data: HabitData) -> Result<String, HabitError> {
         match self.data.get(&name) {
             Some(_) => {
-                Err("Habit already exists with that name!".to_string())
+                Err(HabitError::HabitExists(name))
             },
             None => {
                 self.data.insert(name, data);
@@ -368,76 +624,72 @@ impl UserData {
         }
     }
 
-    pub fn remove_habit(&mut self, name: String) -> Result<String, String> {
+    pub fn remove_habit(&mut self, name: String) -> Result<String, HabitError> {
         match self.data.remove(&name) {
             Some(_) => {
                 Ok("".to_string())
             },
             None => {
-                Err("No habit exists with that name!".to_string())
+                Err(HabitError::HabitNotFound(name))
             },
         }
     }
 
-    pub fn hide_habit(&mut self, name: String) -> Result<String, String> {
+    pub fn hide_habit(&mut self, name: String) -> Result<String, HabitError> {
         match self.data.get_mut(&name) {
             Some(habit) => {
+                let today = HabitID::today();
                 habit.active = !habit.active;
+                if habit.active {
+                    // close the open hidden period; today counts as active again
+                    if let Some((_, end)) = habit.inactive_periods.iter_mut().find(|(_, end)| end.is_none()) {
+                        *end = Some(HabitID::new(today.date() - Duration::days(1)));
+                    }
+                } else {
+                    habit.inactive_periods.push((today, None));
+                }
                 Ok("".to_string())
             },
             None => {
-                Err("No habit with that name exists!".to_string())
+                Err(HabitError::HabitNotFound(name))
             },
         }
     }
 
-    pub fn edit_habit_node(&mut self, args: Vec<String>, date: String, value: i32) -> Result<String, String> {
-        if let Some(habit) = args.get(2).map(|s| s.to_string()) {
-            match self.data.get_mut(&habit) {
-                Some(data) => {
-                    data.edit_node(date, &args[1], value)
-                },
-                None => {
-                    Err("Error: Cannot find data for the specified habit.".to_string())
-                },
-            }
-        } else {
-            Err("Error: Invalid habit input.".to_string())
+    // Reads an amount typed for `habit` in that habit's unit, ex: 1h30m for a habit counted in minutes
+    pub fn parse_amount(&self, habit: &str, text: &str) -> Result<f64, HabitError> {
+        match self.data.get(habit) {
+            Some(data) => data.unit.parse_amount(text),
+            None => Err(HabitError::HabitNotFound(habit.to_string())),
         }
     }
 
-    pub fn habit_list_for_day(&mut self, date: String) -> Result<String, String> {
-        if self.data.len() == 0 {
-            return Err("No habits to list!".to_string())
-        }
-
-        let day;
-        if date == "".to_string() {
-            day = HabitData::get_current_date_id();
-        } else {
-            day = date;
-        }
-
-        println!("Habit list for {}", day);
-        for (key, value) in self.data.iter() {
-            if value.active == true {
-                match value.nodes.get(&day) {
-                    Some(node) => {
-                        println!("{}: {:?} ({}/{})", key, node.status, node.value, node.goal);
-                    },
-                    None => {
-                        // Do nothing?
-                    }
+    // Recording on a day the habit isn't scheduled for needs `force`, and the node then counts as a bonus day.
+    // Resetting is always allowed so stray nodes can be cleared.
+    pub fn edit_habit_node(&mut self, habit: String, command: &str, date: HabitID, value: f64, force: bool) -> Result<String, HabitError> {
+        match self.data.get_mut(&habit) {
+            Some(data) => {
+                if data.is_scheduled(date) || command == "reset" {
+                    data.edit_node(date, command, value)
+                } else if force {
+                    data.edit_node(date, command, value)?;
+                    Ok(format!("{} isn't scheduled on {}, recorded as a bonus day", habit, date))
+                } else {
+                    Err(HabitError::NotScheduled(habit, date.to_string()))
                 }
-            }
+            },
+            None => {
+                Err(HabitError::HabitNotFound(habit))
+            },
         }
-        Ok("".to_string())
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::types::testing::{day, habit_starting};
+    use proptest::prelude::*;
 
     #[test]
     fn it_makes_habit_user() {
@@ -446,7 +698,7 @@ mod tests {
             name: "Ricardo".to_string(),
             data: HashMap::new(),
         };
-        assert_eq!(test_user.id, 007);
+        assert_eq!(test_user.id, 7);
     }
 
     #[test]
@@ -460,16 +712,23 @@ mod tests {
             start_year: 2023,
             start_month: 1,
             start_day: 1,
-            enabled_days: Some(vec![0, 1, 5, 6]),
+            enabled_days: Some(vec![1, 5, 6, 7]),
             description: "this is a test habit".to_string(),
-            goal: 100,
-            nodes: HashMap::new(),
+            goal: 100.0,
+            nodes: BTreeMap::new(),
             metrics: HashMap::new(),
             active: true,
+            missed_status: NodeStatus::IDLE,
+            inactive_periods: Vec::new(),
+            goal_revisions: Vec::new(),
+            unit: Unit::Count,
+            direction: GoalDirection::AtLeast,
+            frequency: Frequency::default(),
+            schedule_revisions: Vec::new(),
         };
         test_user.data.insert("test_habit".to_string(), test_data.clone());
-        let data_check = test_user.data.get(&"test_habit".to_string()).unwrap();
-        assert_eq!(data_check.enabled_days, Some(vec![0, 1, 5, 6]));
+        let data_check = test_user.data.get("test_habit").unwrap();
+        assert_eq!(data_check.enabled_days, Some(vec![1, 5, 6, 7]));
     }
     #[test]
     fn it_adds_and_edits_node() {
@@ -482,26 +741,260 @@ mod tests {
             start_year: 2023,
             start_month: 1,
             start_day: 1,
-            enabled_days: Some(vec![0, 1, 5, 6]),
+            enabled_days: Some(vec![1, 5, 6, 7]),
             description: "this is a test habit".to_string(),
-            goal: 100,
-            nodes: HashMap::new(),
+            goal: 100.0,
+            nodes: BTreeMap::new(),
             metrics: HashMap::new(),
             active: true,
+            missed_status: NodeStatus::IDLE,
+            inactive_periods: Vec::new(),
+            goal_revisions: Vec::new(),
+            unit: Unit::Count,
+            direction: GoalDirection::AtLeast,
+            frequency: Frequency::default(),
+            schedule_revisions: Vec::new(),
         };
         let test_node = HabitNode {
-            value: 10,
-            goal: 100,
+            value: 10.0,
+            goal: 100.0,
             status: NodeStatus::SKIPPED
         };
-        test_data.nodes.insert("10-4-2023".to_string(), test_node);
+        let day = HabitID::from_string("10-4-2023".to_string()).unwrap();
+        test_data.nodes.insert(day, test_node);
         test_user.data.insert("test_habit".to_string(), test_data.clone());
-        assert_eq!(test_user.data.get(&"test_habit".to_string()).unwrap().nodes.get("10-4-2023").unwrap().status, NodeStatus::SKIPPED);
+        assert_eq!(test_user.data.get("test_habit").unwrap().nodes.get(&day).unwrap().status, NodeStatus::SKIPPED);
+
+        let node_check = test_user.data.get_mut("test_habit").unwrap().nodes.get_mut(&day).unwrap();
+        node_check.calculate_status(&GoalDirection::AtLeast);
+
+        assert_eq!(test_user.data.get("test_habit").unwrap().nodes.get(&day).unwrap().status, NodeStatus::PARTIAL);
+    }
+
+    #[test]
+    fn it_reports_typed_errors() {
+        let mut test_user = UserData::new();
+        assert_eq!(test_user.remove_habit("missing".to_string()), Err(HabitError::HabitNotFound("missing".to_string())));
+        assert_eq!(test_user.habit_list_for_day(HabitID::today(), &[], &Palette::new(false)), Err(HabitError::NoHabits));
+
+        let test_data = HabitData::new("desc".to_string(), 1.0, None).unwrap();
+        let _ = test_user.add_habit("test_habit".to_string(), test_data.clone());
+        assert_eq!(test_user.add_habit("test_habit".to_string(), test_data), Err(HabitError::HabitExists("test_habit".to_string())));
+        assert_eq!(test_user.edit_habit_node("test_habit".to_string(), "explode", HabitID::today(), 0.0, false), Err(HabitError::UnknownCommand("explode".to_string())));
+
+        assert_eq!(HabitData::new("desc".to_string(), 1.0, Some("1-x-3".to_string())).err(), Some(HabitError::InvalidEnabledDays("1-x-3".to_string())));
+        assert_eq!(HabitID::from_string("10/4/2023".to_string()), Err(HabitError::InvalidDate("10/4/2023".to_string())));
+
+        // Logging past what an f64 holds is refused and leaves the day as it was
+        let today = HabitID::today();
+        test_user.edit_habit_node("test_habit".to_string(), "increment", today, 1e308, false).unwrap();
+        let overflow = test_user.edit_habit_node("test_habit".to_string(), "increment", today, 1e308, false);
+        assert!(matches!(overflow, Err(HabitError::InvalidArgument(_))));
+        assert_eq!(test_user.data["test_habit"].nodes[&today].value, 1e308);
+        assert!(test_user.data["test_habit"].check_metrics().is_ok());
+    }
+
+    #[test]
+    fn it_normalizes_and_orders_dates() {
+        let padded = HabitID::from_string("10-04-2023".to_string()).unwrap();
+        let plain = HabitID::from_string("10-4-2023".to_string()).unwrap();
+        assert_eq!(padded, plain);
+        assert_eq!(plain.to_string(), "10-4-2023");
+        assert!(HabitID::from_string("2-30-2023".to_string()).is_err());
+        assert!(HabitID::from_string("13-1-2023".to_string()).is_err());
+
+        let mut test_data = HabitData::new("desc".to_string(), 1.0, None).unwrap();
+        for d in ["1-2-2024", "12-31-2023", "1-1-2024"] {
+            test_data.edit_node(HabitID::from_string(d.to_string()).unwrap(), "complete", 0.0).unwrap();
+        }
+        let keys = test_data.nodes.keys().map(|k| k.to_string()).collect::<Vec<String>>();
+        assert_eq!(keys, vec!["12-31-2023", "1-1-2024", "1-2-2024"]);
+
+        let encoded = bincode::serialize(&test_data).unwrap();
+        let decoded: HabitData = bincode::deserialize(&encoded).unwrap();
+        assert_eq!(decoded.nodes.len(), 3);
 
-        let node_check = test_user.data.get_mut(&"test_habit".to_string()).unwrap().nodes.get_mut("10-4-2023").unwrap();
-        node_check.calculate_status();
+        let mut legacy: HashMap<String, i32> = HashMap::new();
+        legacy.insert("10-4-2023".to_string(), 1);
+        let legacy_decoded: BTreeMap<HabitID, i32> = bincode::deserialize(&bincode::serialize(&legacy).unwrap()).unwrap();
+        assert_eq!(legacy_decoded.get(&plain), Some(&1));
+    }
+
+    #[test]
+    fn it_enforces_enabled_days() {
+        assert_eq!(HabitData::validate_allowed_days("5-1-3-3".to_string()), Ok(vec![1, 3, 5]));
+        assert!(HabitData::validate_allowed_days("0-1".to_string()).is_err());
+        assert!(HabitData::validate_allowed_days("1-8".to_string()).is_err());
+
+        let mut test_user = UserData::new();
+        let test_data = HabitData::new("weekdays".to_string(), 1.0, Some("1-2-3-4-5".to_string())).unwrap();
+        let _ = test_user.add_habit("test_habit".to_string(), test_data);
+
+        // 10-7-2023 is a Saturday, 10-9-2023 a Monday
+        let saturday = HabitID::from_string("10-7-2023".to_string()).unwrap();
+        let monday = HabitID::from_string("10-9-2023".to_string()).unwrap();
+        assert_eq!(test_user.edit_habit_node("test_habit".to_string(), "complete", saturday, 0.0, false), Err(HabitError::NotScheduled("test_habit".to_string(), "10-7-2023".to_string())));
+        assert!(test_user.edit_habit_node("test_habit".to_string(), "complete", saturday, 0.0, true).is_ok());
+        assert!(test_user.edit_habit_node("test_habit".to_string(), "complete", monday, 0.0, false).is_ok());
+
+        let data = test_user.data.get("test_habit").unwrap();
+        assert_eq!(data.bonus_days(), 1);
+        assert_eq!(data.scheduled_counts().get(&NodeStatus::COMPLETE), Some(&1));
+    }
+
+    #[test]
+    fn it_fills_missed_days() {
+        // Mon/Wed/Fri, starting Monday 10-2-2023
+        let mut test_data = habit_starting("10-2-2023", 1.0, Some("1-3-5"));
+        test_data.set_missed_status(NodeStatus::FAILED);
+        test_data.edit_node(day("10-4-2023"), "complete", 0.0).unwrap();
+        test_data.inactive_periods.push((day("10-9-2023"), Some(day("10-11-2023"))));
+
+        // Mon 10-2 and Fri 10-6 get filled, 10-9 and 10-11 are hidden, today (Fri 10-13) is left alone
+        assert_eq!(test_data.fill_missed_days(day("10-13-2023")), 2);
+        assert_eq!(test_data.nodes.get(&day("10-2-2023")).unwrap().status, NodeStatus::FAILED);
+        assert_eq!(test_data.nodes.get(&day("10-4-2023")).unwrap().status, NodeStatus::COMPLETE);
+        assert!(!test_data.nodes.contains_key(&day("10-9-2023")));
+        assert!(!test_data.nodes.contains_key(&day("10-13-2023")));
+        assert_eq!(test_data.metrics.get(&NodeStatus::FAILED), Some(&2));
+
+        // running again is a no-op
+        assert_eq!(test_data.fill_missed_days(day("10-13-2023")), 0);
+
+        // A limit kept by logging nothing isn't failed
+        let mut limit = test_data.clone();
+        limit.set_direction(GoalDirection::AtMost).unwrap();
+        limit.nodes.clear();
+        limit.metrics.clear();
+        assert_eq!(limit.fill_missed_days(day("10-13-2023")), 3);
+        assert_eq!(limit.nodes.get(&day("10-2-2023")).unwrap().status, NodeStatus::IDLE);
+        assert_eq!(limit.score_as_of(ScoreStrategy::Strict, day("10-13-2023")).unwrap().value, 1.0);
+
+        // Every day from today on leaves the past alone
+        test_data.set_schedule(None, Frequency::default(), day("10-13-2023"), day("10-13-2023"));
+        assert_eq!(test_data.fill_missed_days(day("10-16-2023")), 3);
+        assert!(!test_data.nodes.contains_key(&day("10-3-2023")));
+        assert_eq!(test_data.metrics.get(&NodeStatus::FAILED), Some(&5));
+
+        // Mondays only from the start: the filled Friday isn't a bonus day, the completed Wednesday is
+        test_data.set_schedule(Some(vec![1]), Frequency::default(), day("10-2-2023"), day("10-16-2023"));
+        assert_eq!(test_data.bonus_days(), 1);
+    }
+
+    #[test]
+    fn it_reads_legacy_user_data() {
+        #[derive(Serialize)]
+        struct OldNode { value: i32, goal: i32, status: NodeStatus }
+        #[derive(Serialize)]
+        struct OldHabit { start_year: u32, start_month: u32, start_day: u32, enabled_days: Option<Vec<u32>>, description: String, goal: i32, nodes: HashMap<String, OldNode>, metrics: HashMap<NodeStatus, i32>, active: bool }
+        #[derive(Serialize)]
+        struct OldUser { id: u32, name: String, data: HashMap<String, OldHabit> }
+
+        let mut nodes = HashMap::new();
+        nodes.insert("10-4-2023".to_string(), OldNode { value: 1, goal: 1, status: NodeStatus::COMPLETE });
+        let mut data = HashMap::new();
+        data.insert("hidden".to_string(), OldHabit { start_year: 2023, start_month: 10, start_day: 1, enabled_days: None, description: "".to_string(), goal: 1, nodes, metrics: HashMap::new(), active: false });
+        let bytes = bincode::serialize(&OldUser { id: 1, name: "".to_string(), data }).unwrap();
+
+        let decoded = UserData::from_bytes(&bytes).unwrap().0;
+        let habit = decoded.data.get("hidden").unwrap();
+        assert_eq!(habit.missed_status, NodeStatus::IDLE);
+        assert!(!habit.is_inactive(HabitID::from_string("10-4-2023".to_string()).unwrap()));
+        assert!(habit.is_inactive(HabitID::from_string("10-5-2023".to_string()).unwrap()));
+
+        assert!(matches!(UserData::from_bytes(&bytes[..bytes.len() / 2]), Err(HabitError::StorageCorrupt(_))));
+    }
+
+    #[test]
+    fn it_repairs_drifted_metrics() {
+        let mut user_data = UserData::new();
+        user_data.add_habit("walk".to_string(), HabitData::new("".to_string(), 1.0, None).unwrap()).unwrap();
+        user_data.edit_habit_node("walk".to_string(), "complete", HabitID::from_string("10-4-2023".to_string()).unwrap(), 0.0, false).unwrap();
+        assert!(user_data.doctor(true).is_ok());
+
+        // Counters as an older build could leave them
+        user_data.data.get_mut("walk").unwrap().metrics.insert(NodeStatus::IDLE, 1);
+        assert!(matches!(user_data.doctor(true), Err(HabitError::InvalidMetric(_))));
+        assert_eq!(user_data.doctor(false).unwrap(), "walk: IDLE stored 1, counted 0\nRepaired 1 habit");
+        assert!(user_data.data.get("walk").unwrap().check_metrics().is_ok());
+    }
 
-        assert_eq!(test_user.data.get(&"test_habit".to_string()).unwrap().nodes.get("10-4-2023").unwrap().status, NodeStatus::PARTIAL);
+    #[test]
+    fn it_keeps_dated_goal_revisions() {
+        let mut user_data = UserData::new();
+        let walk = habit_starting("10-1-2023", 3000.0, None);
+        user_data.add_habit("walk".to_string(), walk).unwrap();
+        user_data.edit_habit_node("walk".to_string(), "complete", day("10-2-2023"), 0.0, false).unwrap();
+        user_data.edit_habit_node("walk".to_string(), "complete", day("10-9-2023"), 0.0, false).unwrap();
+
+        let walk = user_data.data.get_mut("walk").unwrap();
+        walk.set_goal(5000.0, day("10-8-2023")).unwrap();
+        walk.set_goal(8000.0, day("10-15-2023")).unwrap();
+        assert!(walk.set_goal(0.0, day("10-15-2023")).is_err());
+
+        assert_eq!((walk.goal_on(day("10-7-2023")), walk.goal_on(day("10-8-2023")), walk.goal_on(day("10-20-2023"))), (3000.0, 5000.0, 8000.0));
+        // Logged days are left alone, new days pick up the goal of their date
+        assert_eq!(walk.nodes[&day("10-9-2023")].goal, 3000.0);
+        walk.edit_node(day("10-10-2023"), "increment", 4000.0).unwrap();
+        walk.edit_node(day("10-16-2023"), "increment", 4000.0).unwrap();
+        assert_eq!((walk.nodes[&day("10-10-2023")].goal, walk.nodes[&day("10-16-2023")].goal), (5000.0, 8000.0));
+
+        assert_eq!(walk.recalculate_goals(day("10-8-2023")), 1);
+        assert_eq!(walk.nodes[&day("10-9-2023")].goal, 5000.0);
+        assert_eq!(walk.nodes[&day("10-9-2023")].status, NodeStatus::PARTIAL);
+        assert_eq!(walk.nodes[&day("10-2-2023")].status, NodeStatus::COMPLETE);
+        assert!(walk.check_metrics().is_ok());
+    }
+
+    #[test]
+    fn it_judges_days_again_when_the_direction_changes() {
+        let mut coffee = HabitData::new("".to_string(), 2.0, None).unwrap();
+        coffee.edit_node(day("10-2-2023"), "set", 3.0).unwrap();
+        coffee.edit_node(day("10-3-2023"), "set", 1.0).unwrap();
+        coffee.edit_node(day("10-4-2023"), "fail", 0.0).unwrap();
+        coffee.edit_node(day("10-5-2023"), "skip", 0.0).unwrap();
+
+        // Only days whose status came from their value are judged again
+        assert_eq!(coffee.set_direction(GoalDirection::AtMost), Ok(2));
+        let statuses = ["10-2-2023", "10-3-2023", "10-4-2023", "10-5-2023"].map(|d| coffee.nodes[&day(d)].status.clone());
+        assert_eq!(statuses, [NodeStatus::FAILED, NodeStatus::COMPLETE, NodeStatus::FAILED, NodeStatus::SKIPPED]);
+        coffee.edit_node(day("10-6-2023"), "increment", 2.5).unwrap();
+        assert_eq!(coffee.nodes[&day("10-6-2023")].status, NodeStatus::FAILED);
+        assert!(coffee.check_metrics().is_ok());
+
+        // A range's lowest amount has to stay under every goal
+        assert!(coffee.set_direction(GoalDirection::Range(2.0)).is_err());
+        coffee.set_direction(GoalDirection::Range(1.0)).unwrap();
+        assert!(coffee.set_goal(0.5, day("10-7-2023")).is_err());
+        assert_eq!(coffee.nodes[&day("10-3-2023")].status, NodeStatus::COMPLETE);
+    }
+
+    fn edit_command() -> impl Strategy<Value = &'static str> {
+        prop::sample::select(vec!["complete", "fail", "set", "skip", "reset", "increment", "bogus"])
+    }
+
+    proptest! {
+        #[test]
+        fn it_keeps_metrics_equal_to_node_statuses(
+            edits in prop::collection::vec((0i64..30, edit_command(), -10i32..20), 0..60),
+            fill_at in 0i64..40,
+            missed_failed in any::<bool>(),
+        ) {
+            let start = NaiveDate::from_ymd_opt(2023, 10, 1).unwrap();
+            let mut data = habit_starting("10-1-2023", 10.0, Some("1-2-3-5-6"));
+            // Test-built habits may start without any counters at all
+            data.metrics = HashMap::new();
+            if missed_failed {
+                data.set_missed_status(NodeStatus::FAILED);
+            }
+
+            for (offset, command, value) in edits {
+                let _ = data.edit_node(HabitID::new(start + Duration::days(offset)), command, value as f64);
+                prop_assert_eq!(data.check_metrics(), Ok(()));
+            }
+            data.fill_missed_days(HabitID::new(start + Duration::days(fill_at)));
+            prop_assert_eq!(data.check_metrics(), Ok(()));
+        }
     }
 
 }
\ No newline at end of file
@@ -0,0 +1,246 @@
+use chrono::Duration;
+
+use super::frequency::Stretch;
+use super::{HabitData, HabitID, NodeStatus};
+
+// Ways of turning a habit's history into a single 0-1 score. Every strategy walks the same days:
+// scheduled, non-hidden days from the start of the habit up to today. Skipped days are left out,
+// as is today until something is logged, and a day with nothing logged counts as 0, or as 1 for
+// at-most habits, which are kept by not doing something.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ScoreStrategy {
+    Strict,       // share of days that are COMPLETE, partial days count as 0
+    Proportional, // average of how far partial days got toward the goal, capped at 1
+    Strength,     // Loop Habit Tracker style: exponentially weighted, recent days matter most
+}
+
+pub const SCORE_STRATEGIES: [ScoreStrategy; 3] = [ScoreStrategy::Strict, ScoreStrategy::Proportional, ScoreStrategy::Strength];
+
+// Weight kept from the previous strength on each scored day, as in Loop for a daily habit: 0.5^(1/13),
+// so a day's contribution halves after 13 more scored days
+fn strength_decay() -> f64 {
+    0.5_f64.powf(1.0 / 13.0)
+}
+
+impl ScoreStrategy {
+    pub fn name(&self) -> &'static str {
+        match self {
+            ScoreStrategy::Strict => "strict",
+            ScoreStrategy::Proportional => "proportional",
+            ScoreStrategy::Strength => "strength",
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Score {
+    pub value: f64,  // 0 to 1
+    pub days: usize, // days that went into the score, or periods for habits that aren't daily
+}
+
+impl HabitData {
+    // How much of each scored period of `stretch` was done, 0 to 1, in date order. Periods are scored
+    // like days: strict only counts reaching the target, the others how far toward it the period got.
+    fn scored_periods(&self, stretch: &Stretch, today: HabitID, strategy: ScoreStrategy) -> Vec<f64> {
+        self.periods_as_of(stretch, today).iter()
+            .filter_map(|tally| self.judge_period(tally, today).map(|done| match strategy {
+                _ if done => 1.0,
+                ScoreStrategy::Strict => 0.0,
+                _ => (tally.progress / tally.times as f64).min(1.0),
+            }))
+            .collect()
+    }
+
+    // How much of each scored day, or period for habits that aren't daily, was done, 0 to 1, in date
+    // order. Each stretch is scored with the frequency it had, so changing it doesn't rewrite the past.
+    fn scored_days(&self, today: HabitID, strategy: ScoreStrategy) -> Vec<f64> {
+        let mut scored = Vec::new();
+        for stretch in self.stretches().iter().filter(|stretch| stretch.first <= today) {
+            match stretch.frequency.is_daily() {
+                true => scored.extend(self.scored_daily(stretch, today, strategy)),
+                false => scored.extend(self.scored_periods(stretch, today, strategy)),
+            }
+        }
+        scored
+    }
+
+    fn scored_daily(&self, stretch: &Stretch, today: HabitID, strategy: ScoreStrategy) -> Vec<f64> {
+        let end = stretch.last.map_or(today, |last| last.min(today));
+        let mut scored = Vec::new();
+        let mut day = stretch.first.date();
+        while day <= end.date() {
+            let id = HabitID::new(day);
+            day += Duration::days(1);
+
+            if !self.is_tracked(id) {
+                continue;
+            }
+            match self.nodes.get(&id) {
+                Some(node) if node.status == NodeStatus::SKIPPED => {},
+                Some(node) if node.status == NodeStatus::IDLE && id == today => {},
+                None if id == today => {},
+                Some(node) => scored.push(match (strategy, &node.status) {
+                    (_, NodeStatus::COMPLETE) => 1.0,
+                    (_, NodeStatus::IDLE) if self.direction.unlogged_is_done() => 1.0,
+                    (ScoreStrategy::Strict, _) => 0.0,
+                    (_, NodeStatus::PARTIAL) => self.direction.progress(node.value, node.goal),
+                    _ => 0.0,
+                }),
+                None if self.direction.unlogged_is_done() => scored.push(1.0),
+                None => scored.push(0.0),
+            }
+        }
+        scored
+    }
+
+    // None until there is at least one day to score
+    pub fn score_as_of(&self, strategy: ScoreStrategy, today: HabitID) -> Option<Score> {
+        let days = self.scored_days(today, strategy);
+        if days.is_empty() {
+            return None
+        }
+
+        let value = match strategy {
+            ScoreStrategy::Strict | ScoreStrategy::Proportional => days.iter().sum::<f64>() / days.len() as f64,
+            ScoreStrategy::Strength => {
+                let decay = strength_decay();
+                days.iter().fold(0.0, |strength, done| strength * decay + done * (1.0 - decay))
+            },
+        };
+        Some(Score { value, days: days.len() })
+    }
+
+    pub fn score(&self, strategy: ScoreStrategy) -> Option<Score> {
+        self.score_as_of(strategy, HabitID::today())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::testing::{day, habit_starting};
+    use crate::types::{Frequency, GoalDirection};
+
+    fn sample() -> HabitData {
+        let mut data = habit_starting("10-1-2023", 10.0, None);
+        for d in ["10-1-2023", "10-2-2023", "10-3-2023"] {
+            data.edit_node(day(d), "complete", 0.0).unwrap();
+        }
+        data.edit_node(day("10-4-2023"), "set", 5.0).unwrap();
+        data.edit_node(day("10-5-2023"), "fail", 0.0).unwrap();
+        data.edit_node(day("10-6-2023"), "skip", 0.0).unwrap();
+        data
+    }
+
+    #[test]
+    fn it_scores_strictly() {
+        let score = sample().score_as_of(ScoreStrategy::Strict, day("10-6-2023")).unwrap();
+        assert_eq!(score, Score { value: 0.6, days: 5 });
+    }
+
+    #[test]
+    fn it_scores_proportionally() {
+        let score = sample().score_as_of(ScoreStrategy::Proportional, day("10-6-2023")).unwrap();
+        assert_eq!(score, Score { value: 0.7, days: 5 });
+
+        // Going over the goal doesn't make up for other days
+        let mut data = habit_starting("10-1-2023", 10.0, None);
+        data.edit_node(day("10-1-2023"), "set", 30.0).unwrap();
+        data.edit_node(day("10-2-2023"), "set", 0.0).unwrap();
+        assert_eq!(data.score_as_of(ScoreStrategy::Proportional, day("10-2-2023")).unwrap().value, 0.5);
+    }
+
+    #[test]
+    fn it_scores_strength() {
+        let mut data = habit_starting("10-1-2023", 1.0, None);
+        assert!(data.score_as_of(ScoreStrategy::Strength, day("10-1-2023")).is_none());
+
+        for d in 1..=30 {
+            data.edit_node(day(&format!("10-{}-2023", d)), "complete", 0.0).unwrap();
+        }
+        let day_one = data.score_as_of(ScoreStrategy::Strength, day("10-1-2023")).unwrap().value;
+        let month = data.score_as_of(ScoreStrategy::Strength, day("10-30-2023")).unwrap().value;
+        assert!((day_one - (1.0 - strength_decay())).abs() < 1e-9);
+        assert!((month - (1.0 - strength_decay().powi(30))).abs() < 1e-9);
+
+        // A missed day costs more the more recent it is
+        let mut early = data.clone();
+        early.edit_node(day("10-2-2023"), "fail", 0.0).unwrap();
+        let mut late = data.clone();
+        late.edit_node(day("10-29-2023"), "fail", 0.0).unwrap();
+        assert!(late.score_as_of(ScoreStrategy::Strength, day("10-30-2023")).unwrap().value
+            < early.score_as_of(ScoreStrategy::Strength, day("10-30-2023")).unwrap().value);
+    }
+
+    #[test]
+    fn it_leaves_out_skipped_unscheduled_and_unlogged_days() {
+        // Mondays only, 10-2-2023 is a Monday; the bonus Tuesday doesn't count
+        let mut data = habit_starting("10-1-2023", 1.0, Some("1"));
+        data.edit_node(day("10-2-2023"), "complete", 0.0).unwrap();
+        data.edit_node(day("10-3-2023"), "fail", 0.0).unwrap();
+        data.edit_node(day("10-9-2023"), "skip", 0.0).unwrap();
+        for strategy in SCORE_STRATEGIES {
+            let score = data.score_as_of(strategy, day("10-16-2023")).unwrap();
+            assert_eq!(score.days, 1, "{:?}", strategy);
+        }
+
+        // A missed Monday with nothing logged counts as 0, an unlogged today doesn't count yet
+        assert_eq!(data.score_as_of(ScoreStrategy::Strict, day("10-23-2023")).unwrap(), Score { value: 0.5, days: 2 });
+
+        assert_eq!(habit_starting("10-1-2023", 1.0, None).score_as_of(ScoreStrategy::Strict, day("9-1-2023")), None);
+    }
+
+    #[test]
+    fn it_scores_limits_by_staying_under_them() {
+        // At most 2: under the limit, over it, and two days with nothing logged
+        let mut data = habit_starting("10-1-2023", 2.0, None);
+        data.set_direction(GoalDirection::AtMost).unwrap();
+        data.edit_node(day("10-1-2023"), "set", 1.0).unwrap();
+        data.edit_node(day("10-2-2023"), "set", 3.0).unwrap();
+        data.insert_fresh_node(day("10-3-2023")).unwrap();
+        for strategy in [ScoreStrategy::Strict, ScoreStrategy::Proportional] {
+            assert_eq!(data.score_as_of(strategy, day("10-5-2023")).unwrap(), Score { value: 0.75, days: 4 }, "{:?}", strategy);
+        }
+
+        // Short of a range's lowest amount is partial, scored against that amount
+        let mut data = habit_starting("10-1-2023", 9.0, None);
+        data.set_direction(GoalDirection::Range(7.0)).unwrap();
+        data.edit_node(day("10-1-2023"), "set", 3.5).unwrap();
+        assert_eq!(data.score_as_of(ScoreStrategy::Proportional, day("10-1-2023")).unwrap().value, 0.5);
+    }
+
+    #[test]
+    fn it_scores_weekly_habits_by_the_week() {
+        // Twice a week. The week the habit started in, a week with a skip and the current week all
+        // fell short, so only the weeks of 10-2 and 10-9 count.
+        let mut data = habit_starting("10-1-2023", 10.0, None);
+        data.set_frequency(Frequency::parse("2/week").unwrap()).unwrap();
+        for d in ["10-2-2023", "10-4-2023", "10-10-2023"] {
+            data.edit_node(day(d), "complete", 0.0).unwrap();
+        }
+        data.edit_node(day("10-11-2023"), "set", 5.0).unwrap();
+        data.edit_node(day("10-16-2023"), "skip", 0.0).unwrap();
+        data.edit_node(day("10-23-2023"), "complete", 0.0).unwrap();
+
+        assert_eq!(data.score_as_of(ScoreStrategy::Strict, day("10-24-2023")).unwrap(), Score { value: 0.5, days: 2 });
+        assert_eq!(data.score_as_of(ScoreStrategy::Proportional, day("10-24-2023")).unwrap(), Score { value: 0.875, days: 2 });
+    }
+
+    #[test]
+    fn it_scores_past_days_by_the_schedule_they_had() {
+        // Mon/Wed/Fri, then every day from Monday 10-9-2023
+        let mut data = habit_starting("10-1-2023", 1.0, Some("1-3-5"));
+        for d in ["10-2-2023", "10-4-2023", "10-6-2023"] {
+            data.edit_node(day(d), "complete", 0.0).unwrap();
+        }
+        let before = data.score_as_of(ScoreStrategy::Strict, day("10-8-2023"));
+        assert_eq!(data.set_schedule(None, Frequency::default(), day("10-9-2023"), day("10-9-2023")), 0);
+        assert_eq!(data.score_as_of(ScoreStrategy::Strict, day("10-8-2023")), before);
+
+        // The missed Monday and Tuesday count from the change on
+        assert_eq!(data.score_as_of(ScoreStrategy::Strict, day("10-11-2023")).unwrap(), Score { value: 0.6, days: 5 });
+
+        // Backdated to the first Monday, the Tuesday, Thursday, Saturday and Sunday before the change are judged again
+        assert_eq!(data.set_schedule(None, Frequency::default(), day("10-2-2023"), day("10-9-2023")), 4);
+    }
+}
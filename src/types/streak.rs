@@ -0,0 +1,187 @@
+use chrono::Duration;
+
+use super::frequency::Stretch;
+use super::{Frequency, HabitData, HabitID, NodeStatus};
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Streaks {
+    pub current: u32,
+    pub longest: u32,
+    pub longest_range: Option<(HabitID, HabitID)>, // first and last day of the longest streak
+    pub longest_frequency: Frequency,              // what the longest streak is counted in, days or periods
+}
+
+impl HabitData {
+    pub fn streaks(&self) -> Streaks {
+        self.streaks_as_of(HabitID::today())
+    }
+
+    // Walks every day, or period for habits that aren't daily, from the start of the habit up to `today`.
+    // Each stretch is walked with the frequency it had, and a change of frequency starts a new streak,
+    // since days and periods can't be added up.
+    pub fn streaks_as_of(&self, today: HabitID) -> Streaks {
+        let mut streaks = Streaks { current: 0, longest: 0, longest_range: None, longest_frequency: self.frequency_on(today) };
+
+        for stretch in self.stretches().iter().filter(|stretch| stretch.first <= today) {
+            let judged = match stretch.frequency.is_daily() {
+                true => self.judged_days(stretch, today),
+                false => self.periods_as_of(stretch, today).iter()
+                    .map(|tally| (tally.first, tally.last, self.judge_period(tally, today)))
+                    .collect(),
+            };
+
+            let mut run: u32 = 0;
+            let mut run_start = stretch.first;
+            for (first, last, done) in judged {
+                match done {
+                    Some(true) => {
+                        if run == 0 {
+                            run_start = first;
+                        }
+                        run += 1;
+                        if run > streaks.longest {
+                            streaks.longest = run;
+                            streaks.longest_range = Some((run_start, last));
+                            streaks.longest_frequency = stretch.frequency;
+                        }
+                    },
+                    Some(false) => {
+                        run = 0;
+                    },
+                    None => {},
+                }
+            }
+            streaks.current = run;
+        }
+        streaks
+    }
+
+    // Each day of a daily stretch with whether it extends the streak. Completed days extend it,
+    // skipped, unscheduled and hidden days leave it untouched, and anything else on a scheduled day breaks it.
+    // Today only counts once it's complete, so an unfinished today doesn't reset the current streak,
+    // but a today marked failed does.
+    // At-most habits are kept by not doing something, so past days with nothing logged extend the streak too.
+    fn judged_days(&self, stretch: &Stretch, today: HabitID) -> Vec<(HabitID, HabitID, Option<bool>)> {
+        let end = stretch.last.map_or(today, |last| last.min(today));
+        let mut judged = Vec::new();
+        let mut day = stretch.first.date();
+
+        while day <= end.date() {
+            let id = HabitID::new(day);
+            day += Duration::days(1);
+
+            if !self.is_tracked(id) {
+                continue;
+            }
+
+            let status = self.nodes.get(&id).map(|n| &n.status);
+            let done = match status {
+                Some(NodeStatus::COMPLETE) => true,
+                Some(NodeStatus::IDLE) | None => self.direction.unlogged_is_done() && id != today,
+                _ => false,
+            };
+            let judgement = match status {
+                _ if done => Some(true),
+                Some(NodeStatus::SKIPPED) => None,
+                Some(NodeStatus::IDLE) | Some(NodeStatus::PARTIAL) | None if id == today => None,
+                _ => Some(false),
+            };
+            judged.push((id, id, judgement));
+        }
+        judged
+    }
+
+    pub fn streak_summary(&self) -> String {
+        let streaks = self.streaks();
+        let longest = match streaks.longest_range {
+            Some((from, to)) => format!("{} ({} to {})", streaks.longest_frequency.count(streaks.longest as usize), from, to),
+            None => streaks.longest_frequency.count(0),
+        };
+        format!("Current streak: {}\nLongest streak: {}", self.frequency_on(HabitID::today()).count(streaks.current as usize), longest)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::testing::{day, habit_starting};
+    use crate::types::{Frequency, GoalDirection};
+
+    #[test]
+    fn it_counts_current_and_longest_streaks() {
+        let mut data = habit_starting("10-1-2023", 1.0, None);
+        for d in ["10-1-2023", "10-2-2023", "10-3-2023", "10-5-2023", "10-7-2023", "10-8-2023"] {
+            data.edit_node(day(d), "complete", 0.0).unwrap();
+        }
+        data.edit_node(day("10-6-2023"), "skip", 0.0).unwrap();
+        data.edit_node(day("10-4-2023"), "fail", 0.0).unwrap();
+
+        let streaks = data.streaks_as_of(day("10-9-2023"));
+        assert_eq!(streaks.current, 3);
+        assert_eq!(streaks.longest, 3);
+        assert_eq!(streaks.longest_range, Some((day("10-1-2023"), day("10-3-2023"))));
+
+        let streaks = data.streaks_as_of(day("10-10-2023"));
+        assert_eq!(streaks.current, 0);
+
+        // Today still in progress keeps the streak, today failed ends it
+        data.edit_node(day("10-9-2023"), "set", 0.5).unwrap();
+        assert_eq!(data.streaks_as_of(day("10-9-2023")).current, 3);
+        data.edit_node(day("10-9-2023"), "fail", 0.0).unwrap();
+        assert_eq!(data.streaks_as_of(day("10-9-2023")).current, 0);
+    }
+
+    #[test]
+    fn it_summarizes_streaks() {
+        let data = HabitData::new("".to_string(), 1.0, None).unwrap();
+        assert_eq!(data.streak_summary(), "Current streak: 0 days\nLongest streak: 0 days");
+    }
+
+    #[test]
+    fn it_ignores_unscheduled_days() {
+        // Mondays and Wednesdays only; 10-2-2023 is a Monday
+        let mut data = habit_starting("10-2-2023", 1.0, Some("1-3"));
+        for d in ["10-2-2023", "10-4-2023", "10-9-2023"] {
+            data.edit_node(day(d), "complete", 0.0).unwrap();
+        }
+
+        let streaks = data.streaks_as_of(day("10-10-2023"));
+        assert_eq!(streaks.current, 3);
+        assert_eq!(streaks.longest_range, Some((day("10-2-2023"), day("10-9-2023"))));
+    }
+
+    #[test]
+    fn it_keeps_limit_streaks_until_the_limit_is_broken() {
+        let mut data = habit_starting("10-1-2023", 1.0, None);
+        data.set_direction(GoalDirection::AtMost).unwrap();
+        data.edit_node(day("10-2-2023"), "set", 1.0).unwrap();
+        data.edit_node(day("10-4-2023"), "set", 2.0).unwrap();
+
+        // Nothing logged on 10-1 and 10-3 stays under the limit, going over on 10-4 breaks the streak
+        let streaks = data.streaks_as_of(day("10-7-2023"));
+        assert_eq!(data.nodes[&day("10-4-2023")].status, NodeStatus::FAILED);
+        assert_eq!(streaks.longest_range, Some((day("10-1-2023"), day("10-3-2023"))));
+        assert_eq!(streaks.current, 2);
+    }
+
+    #[test]
+    fn it_counts_streaks_in_periods() {
+        // Three times a week from Monday 10-2-2023; the week of 10-9 falls short
+        let mut data = habit_starting("10-2-2023", 1.0, None);
+        data.set_frequency(Frequency::parse("3/week").unwrap()).unwrap();
+        for d in ["10-2-2023", "10-3-2023", "10-6-2023", "10-9-2023", "10-16-2023", "10-18-2023", "10-22-2023", "10-23-2023"] {
+            data.edit_node(day(d), "complete", 0.0).unwrap();
+        }
+
+        // The week of 10-23 isn't done yet, so it neither extends nor breaks the streak
+        let streaks = data.streaks_as_of(day("10-25-2023"));
+        assert_eq!(streaks.current, 1);
+        assert_eq!(streaks.longest_range, Some((day("10-2-2023"), day("10-8-2023"))));
+
+        // A skipped day excuses the week it's in
+        data.edit_node(day("10-10-2023"), "skip", 0.0).unwrap();
+        let streaks = data.streaks_as_of(day("10-25-2023"));
+        assert_eq!(streaks.current, 2);
+        assert_eq!(streaks.longest_range, Some((day("10-2-2023"), day("10-22-2023"))));
+    }
+}
@@ -0,0 +1,80 @@
+use std::env;
+use std::io::IsTerminal;
+use ansi_term::{Colour, Style};
+
+use super::NodeStatus;
+
+// Colors for terminal output. Plain text when `color` is off, so output can be piped or tested.
+#[derive(Clone, Copy, Debug)]
+pub struct Palette {
+    color: bool,
+}
+
+impl Palette {
+    pub fn new(color: bool) -> Self {
+        Palette { color }
+    }
+
+    // Color only when stdout is a terminal and NO_COLOR (https://no-color.org) isn't set
+    pub fn for_stdout() -> Self {
+        let no_color = env::var_os("NO_COLOR").is_some_and(|v| !v.is_empty());
+        Palette::new(std::io::stdout().is_terminal() && !no_color)
+    }
+
+    pub fn paint(&self, style: Style, text: &str) -> String {
+        match self.color {
+            true => style.paint(text).to_string(),
+            false => text.to_string(),
+        }
+    }
+
+    pub fn bold(&self, text: &str) -> String {
+        self.paint(Style::new().bold(), text)
+    }
+
+    pub fn dim(&self, text: &str) -> String {
+        self.paint(Style::new().dimmed(), text)
+    }
+
+    pub fn status(&self, status: Option<&NodeStatus>, text: &str) -> String {
+        self.paint(status_style(status), text)
+    }
+}
+
+// None stands for a scheduled day that hasn't been logged yet
+pub fn status_style(status: Option<&NodeStatus>) -> Style {
+    match status {
+        Some(NodeStatus::COMPLETE) => Colour::Green.normal(),
+        Some(NodeStatus::PARTIAL) => Colour::Yellow.normal(),
+        Some(NodeStatus::FAILED) => Colour::Red.normal(),
+        Some(NodeStatus::SKIPPED) => Colour::Blue.normal(),
+        Some(NodeStatus::IDLE) => Style::new().dimmed(),
+        None => Style::new(),
+    }
+}
+
+pub fn status_symbol(status: Option<&NodeStatus>) -> &'static str {
+    match status {
+        Some(NodeStatus::COMPLETE) => "✔",
+        Some(NodeStatus::PARTIAL) => "◐",
+        Some(NodeStatus::FAILED) => "✘",
+        Some(NodeStatus::SKIPPED) => "–",
+        Some(NodeStatus::IDLE) => "·",
+        None => "○",
+    }
+}
+
+// Fixed-width bar showing how much of `goal` is done, ex: ███░░░░░░░
+pub fn progress_bar(value: f64, goal: f64, width: usize) -> String {
+    let filled = match goal > 0.0 {
+        true => ((value.max(0.0) / goal) * width as f64).round().min(width as f64) as usize,
+        false => width,
+    };
+    format!("{}{}", "█".repeat(filled), "░".repeat(width - filled))
+}
+
+// Pads by characters rather than bytes, so names with symbols or accents still line up
+pub fn pad(text: &str, width: usize) -> String {
+    let len = text.chars().count();
+    format!("{}{}", text, " ".repeat(width.saturating_sub(len)))
+}
@@ -0,0 +1,19 @@
+// Helpers shared by the tests in this module
+use chrono::Datelike;
+
+use super::{HabitData, HabitID};
+
+// A day in the month-day-year form typed on the command line, ex: 10-4-2023
+pub fn day(s: &str) -> HabitID {
+    HabitID::from_string(s.to_string()).unwrap()
+}
+
+// A habit with `goal` on `days` (every day when None) that started on `start` rather than today
+pub fn habit_starting(start: &str, goal: f64, days: Option<&str>) -> HabitData {
+    let mut data = HabitData::new("".to_string(), goal, days.map(|d| d.to_string())).unwrap();
+    let start = day(start).date();
+    data.start_year = start.year() as u32;
+    data.start_month = start.month();
+    data.start_day = start.day();
+    data
+}
@@ -0,0 +1,226 @@
+use serde::{Deserialize, Serialize};
+
+use super::HabitError;
+
+// What a habit's values measure. Values are stored in the habit's own unit, and amounts typed in
+// another unit of the same kind (ex: 1h30m for a habit counted in minutes) are converted into it.
+// Stored as the unit's name, or nothing for a plain count, so it reads the same as the old label.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(from = "Option<String>", into = "Option<String>")]
+pub enum Unit {
+    #[default]
+    Count,
+    Minutes,
+    Hours,
+    Meters,
+    Kilometers,
+    Miles,
+    Milliliters,
+    Liters,
+    Custom(String), // a label only, ex: steps
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Kind {
+    Time,     // measured in minutes
+    Distance, // measured in meters
+    Volume,   // measured in milliliters
+}
+
+// Suffixes accepted when typing an amount, with their size in the kind's base unit
+const SUFFIXES: [(Kind, &[&str], f64); 10] = [
+    (Kind::Time, &["s", "sec", "secs", "second", "seconds"], 1.0 / 60.0),
+    (Kind::Time, &["m", "min", "mins", "minute", "minutes"], 1.0),
+    (Kind::Time, &["h", "hr", "hrs", "hour", "hours"], 60.0),
+    (Kind::Distance, &["m", "meter", "meters", "metre", "metres"], 1.0),
+    (Kind::Distance, &["km", "kilometer", "kilometers", "kilometre", "kilometres"], 1000.0),
+    (Kind::Distance, &["mi", "mile", "miles"], 1609.344),
+    (Kind::Volume, &["ml", "milliliter", "milliliters", "millilitre", "millilitres"], 1.0),
+    (Kind::Volume, &["cl"], 10.0),
+    (Kind::Volume, &["dl"], 100.0),
+    (Kind::Volume, &["l", "liter", "liters", "litre", "litres"], 1000.0),
+];
+
+// Keeps sums like 0.1 + 0.2 from landing just under a goal. Values too large to scale have no
+// fractional part left to tidy.
+pub fn tidy(value: f64) -> f64 {
+    match (value * 1_000_000.0).round() / 1_000_000.0 {
+        tidied if tidied.is_finite() => tidied,
+        _ => value,
+    }
+}
+
+// Whole numbers without a decimal point, others with up to two decimals, ex: 5000, 2.5, 0.33
+pub fn format_number(value: f64) -> String {
+    let text = format!("{:.2}", value);
+    text.trim_end_matches('0').trim_end_matches('.').to_string()
+}
+
+impl Unit {
+    // Reads a unit from its name or a common spelling of it; anything else becomes a custom label
+    pub fn from_name(name: &str) -> Unit {
+        match name.trim().to_lowercase().as_str() {
+            "" | "none" | "count" => Unit::Count,
+            "min" | "mins" | "minute" | "minutes" => Unit::Minutes,
+            "h" | "hr" | "hrs" | "hour" | "hours" => Unit::Hours,
+            "m" | "meter" | "meters" | "metre" | "metres" => Unit::Meters,
+            "km" | "kilometer" | "kilometers" | "kilometre" | "kilometres" => Unit::Kilometers,
+            "mi" | "mile" | "miles" => Unit::Miles,
+            "ml" | "milliliter" | "milliliters" | "millilitre" | "millilitres" => Unit::Milliliters,
+            "l" | "liter" | "liters" | "litre" | "litres" => Unit::Liters,
+            _ => Unit::Custom(name.trim().to_string()),
+        }
+    }
+
+    // Shown after values; None for a plain count
+    pub fn suffix(&self) -> Option<&str> {
+        match self {
+            Unit::Count => None,
+            Unit::Minutes => Some("min"),
+            Unit::Hours => Some("h"),
+            Unit::Meters => Some("m"),
+            Unit::Kilometers => Some("km"),
+            Unit::Miles => Some("mi"),
+            Unit::Milliliters => Some("ml"),
+            Unit::Liters => Some("L"),
+            Unit::Custom(label) => Some(label),
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        self.suffix().unwrap_or("count")
+    }
+
+    // Kind and size in the kind's base unit, None for counts and custom labels
+    fn measure(&self) -> Option<(Kind, f64)> {
+        match self {
+            Unit::Minutes => Some((Kind::Time, 1.0)),
+            Unit::Hours => Some((Kind::Time, 60.0)),
+            Unit::Meters => Some((Kind::Distance, 1.0)),
+            Unit::Kilometers => Some((Kind::Distance, 1000.0)),
+            Unit::Miles => Some((Kind::Distance, 1609.344)),
+            Unit::Milliliters => Some((Kind::Volume, 1.0)),
+            Unit::Liters => Some((Kind::Volume, 1000.0)),
+            Unit::Count | Unit::Custom(_) => None,
+        }
+    }
+
+    // What a value in this unit is multiplied by to express it in `other`, when both measure the same thing
+    pub fn factor_to(&self, other: &Unit) -> Option<f64> {
+        match (self.measure(), other.measure()) {
+            (Some((kind, size)), Some((other_kind, other_size))) if kind == other_kind => Some(size / other_size),
+            _ => None,
+        }
+    }
+
+    // Reads an amount typed by the user into this unit. A bare number is already in this unit;
+    // otherwise the text is one or more number and suffix pairs of the same kind, ex: 1h30m, 2.5km, 500ml.
+    // Amounts are never negative, and ones too large to store, ex: 1e400, are refused.
+    pub fn parse_amount(&self, text: &str) -> Result<f64, HabitError> {
+        let value = self.read_amount(text)?;
+        if !value.is_finite() {
+            return Err(HabitError::InvalidArgument(format!("'{}' is too large an amount", text)))
+        }
+        if value < 0.0 {
+            return Err(HabitError::InvalidArgument(format!("amounts can't be negative, got '{}'", text)))
+        }
+        Ok(value)
+    }
+
+    fn read_amount(&self, text: &str) -> Result<f64, HabitError> {
+        let invalid = || HabitError::InvalidArgument(format!("can't read '{}' as an amount in {}", text, self.name()));
+        let compact = text.trim().to_lowercase().replace(' ', "");
+        if let Ok(value) = compact.parse::<f64>() {
+            return Ok(tidy(value))
+        }
+        // A custom label can be typed after the number, ex: 5000steps
+        if let Unit::Custom(label) = self {
+            if let Some(value) = compact.strip_suffix(&label.to_lowercase().replace(' ', "")).and_then(|n| n.parse::<f64>().ok()) {
+                return Ok(tidy(value))
+            }
+        }
+
+        let (kind, size) = self.measure().ok_or_else(invalid)?;
+        let mut total = 0.0;
+        let mut rest = compact.as_str();
+        let mut negative = false;
+        if let Some(stripped) = rest.strip_prefix('-') {
+            negative = true;
+            rest = stripped;
+        }
+        while !rest.is_empty() {
+            let number_end = rest.find(|c: char| !(c.is_ascii_digit() || c == '.')).ok_or_else(invalid)?;
+            let suffix_end = rest[number_end..].find(|c: char| c.is_ascii_digit() || c == '.').map_or(rest.len(), |i| number_end + i);
+            let number = rest[..number_end].parse::<f64>().map_err(|_| invalid())?;
+            let suffix = &rest[number_end..suffix_end];
+            let factor = SUFFIXES.iter()
+                .find(|(k, names, _)| *k == kind && names.contains(&suffix))
+                .map(|(_, _, factor)| *factor)
+                .ok_or_else(invalid)?;
+            total += number * factor;
+            rest = &rest[suffix_end..];
+        }
+        Ok(tidy(if negative { -total } else { total } / size))
+    }
+
+    // A value with its unit, ex: 2.5 km, 5000 steps, or just 3 for a count
+    pub fn format(&self, value: f64) -> String {
+        match self.suffix() {
+            Some(suffix) => format!("{} {}", format_number(value), suffix),
+            None => format_number(value),
+        }
+    }
+}
+
+impl From<Option<String>> for Unit {
+    fn from(name: Option<String>) -> Self {
+        match name {
+            Some(name) => Unit::from_name(&name),
+            None => Unit::Count,
+        }
+    }
+}
+
+impl From<Unit> for Option<String> {
+    fn from(unit: Unit) -> Self {
+        unit.suffix().map(|suffix| suffix.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_reads_amounts_into_the_habit_unit() {
+        assert_eq!(Unit::Minutes.parse_amount("1h30m"), Ok(90.0));
+        assert_eq!(Unit::Minutes.parse_amount("45"), Ok(45.0));
+        assert_eq!(Unit::Hours.parse_amount("1h 30min"), Ok(1.5));
+        assert_eq!(Unit::Hours.parse_amount("90m"), Ok(1.5));
+        assert_eq!(Unit::Meters.parse_amount("2.5km"), Ok(2500.0));
+        assert_eq!(Unit::Kilometers.parse_amount("800m"), Ok(0.8));
+        assert_eq!(Unit::Liters.parse_amount("500ml"), Ok(0.5));
+        assert_eq!(Unit::Custom("steps".to_string()).parse_amount("5000 steps"), Ok(5000.0));
+        assert_eq!(Unit::Count.parse_amount("2.5"), Ok(2.5));
+
+        for (unit, text) in [(Unit::Count, "2km"), (Unit::Minutes, "2km"), (Unit::Liters, "l"), (Unit::Hours, "1h30"), (Unit::Count, "inf"), (Unit::Count, "1e400"),
+            (Unit::Count, "-5"), (Unit::Liters, "-250ml"), (Unit::Custom("steps".to_string()), "1e400steps")] {
+            assert!(matches!(unit.parse_amount(text), Err(HabitError::InvalidArgument(_))), "{}", text);
+        }
+    }
+
+    #[test]
+    fn it_names_and_formats_units() {
+        assert_eq!(Unit::from_name("Hours"), Unit::Hours);
+        assert_eq!(Unit::from_name("none"), Unit::Count);
+        assert_eq!(Unit::from_name("kcal"), Unit::Custom("kcal".to_string()));
+        assert_eq!(Unit::from_name(Unit::Liters.name()), Unit::Liters);
+        assert_eq!(Unit::Hours.factor_to(&Unit::Minutes), Some(60.0));
+        assert_eq!(Unit::Hours.factor_to(&Unit::Kilometers), None);
+        assert_eq!(Unit::Liters.format(2.5), "2.5 L");
+        assert_eq!(Unit::Custom("steps".to_string()).format(5000.0), "5000 steps");
+        assert_eq!(Unit::Count.format(1.0 / 3.0), "0.33");
+        assert_eq!(format_number(tidy(0.1 + 0.2)), "0.3");
+        assert_eq!(tidy(1e308), 1e308);
+    }
+}
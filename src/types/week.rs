@@ -0,0 +1,150 @@
+use chrono::{Datelike, Duration};
+use ansi_term::Style;
+
+use super::{format_number, HabitData, HabitError, HabitID, NodeStatus, Palette, UserData};
+use super::style::{pad, status_style, status_symbol};
+
+const DAY_NAMES: [&str; 7] = ["Mon", "Tue", "Wed", "Thu", "Fri", "Sat", "Sun"];
+
+// One cell of the week grid
+struct WeekCell {
+    style: Style,
+    text: String,
+    done: Option<bool>, // None when the day doesn't count toward the weekly rate
+}
+
+impl HabitData {
+    // Scheduled days count toward the rate once they're over, or once they're logged. Skipped days,
+    // bonus days, hidden days and an unlogged today don't count. Past days with nothing logged count as
+    // done for at-most habits, and don't count for habits that aren't daily.
+    fn week_cell(&self, day: HabitID, today: HabitID) -> WeekCell {
+        let tracked = self.is_tracked(day);
+
+        match self.nodes.get(&day) {
+            Some(node) => {
+                let mut text = status_symbol(Some(&node.status)).to_string();
+                if self.is_numeric() && node.value > 0.0 {
+                    text.push_str(&format!(" {}", format_number(node.value)));
+                }
+                let done = match node.status {
+                    _ if !tracked => None,
+                    NodeStatus::SKIPPED => None,
+                    NodeStatus::IDLE if day == today => None,
+                    NodeStatus::IDLE if !self.frequency_on(day).is_daily() => None,
+                    NodeStatus::IDLE => Some(self.direction.unlogged_is_done()),
+                    NodeStatus::COMPLETE => Some(true),
+                    _ => Some(false),
+                };
+                let style = status_style(Some(&node.status));
+                WeekCell { style: if tracked { style } else { style.dimmed() }, text, done }
+            },
+            None if day > today || day < self.start_date() => WeekCell { style: Style::new(), text: String::new(), done: None },
+            None if !tracked || !self.frequency_on(day).is_daily() => WeekCell { style: Style::new().dimmed(), text: ".".to_string(), done: None },
+            None if day == today => WeekCell { style: Style::new(), text: status_symbol(None).to_string(), done: None },
+            None => WeekCell { style: Style::new(), text: status_symbol(None).to_string(), done: Some(self.direction.unlogged_is_done()) },
+        }
+    }
+}
+
+fn rate(done: usize, counted: usize) -> String {
+    match counted {
+        0 => "–".to_string(),
+        _ => format!("{}/{} {:.0}%", done, counted, done as f64 * 100.0 / counted as f64),
+    }
+}
+
+impl UserData {
+    // Grid of every active habit against the Monday to Sunday week holding `day`, with each habit's
+    // completion rate for the week and a total row counting completed days per day
+    pub fn week_grid(&self, day: HabitID, today: HabitID, order: &[String], palette: &Palette) -> Result<String, HabitError> {
+        let names = self.ordered_names(order);
+        if names.is_empty() {
+            return Err(HabitError::NoHabits)
+        }
+
+        // The first and last weeks the calendar can hold may run off its ends
+        let monday = day.date().checked_sub_signed(Duration::days(day.date().weekday().num_days_from_monday() as i64));
+        let days = (0..7).map(|d| monday.and_then(|monday| monday.checked_add_signed(Duration::days(d))).map(HabitID::new))
+            .collect::<Option<Vec<HabitID>>>()
+            .ok_or_else(|| HabitError::InvalidDate(day.to_string()))?;
+
+        let rows = names.iter().map(|name| {
+            let habit = &self.data[*name];
+            (name.as_str(), days.iter().map(|d| habit.week_cell(*d, today)).collect::<Vec<WeekCell>>())
+        }).collect::<Vec<(&str, Vec<WeekCell>)>>();
+
+        let name_width = names.iter().map(|name| name.chars().count()).chain(["Total".len()]).max().unwrap_or(0);
+        let cell_width = rows.iter().flat_map(|(_, cells)| cells.iter().map(|c| c.text.chars().count())).max().unwrap_or(0).max(5);
+
+        let mut out = vec![palette.bold(&format!("Week of {} to {}", days[0], days[6]))];
+        let header = DAY_NAMES.iter().map(|d| pad(d, cell_width)).collect::<Vec<String>>().join("  ");
+        out.push(palette.bold(format!("{}  {}  Rate", pad("", name_width), header).trim_end()));
+
+        let mut day_done = [0; 7];
+        let mut day_counted = [0; 7];
+        for (name, cells) in rows.iter() {
+            let mut line = pad(name, name_width);
+            let (mut done, mut counted) = (0, 0);
+            for (i, cell) in cells.iter().enumerate() {
+                line.push_str("  ");
+                line.push_str(&palette.paint(cell.style, &pad(&cell.text, cell_width)));
+                if let Some(is_done) = cell.done {
+                    counted += 1;
+                    day_counted[i] += 1;
+                    if is_done {
+                        done += 1;
+                        day_done[i] += 1;
+                    }
+                }
+            }
+            line.push_str(&format!("  {}", rate(done, counted)));
+            out.push(line);
+        }
+
+        let mut total = pad("Total", name_width);
+        for i in 0..7 {
+            let text = if day_counted[i] > 0 { format!("{}/{}", day_done[i], day_counted[i]) } else { String::new() };
+            total.push_str(&format!("  {}", pad(&text, cell_width)));
+        }
+        total.push_str(&format!("  {}", rate(day_done.iter().sum(), day_counted.iter().sum())));
+        out.push(palette.bold(&total));
+
+        Ok(out.join("\n"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::testing::{day, habit_starting};
+
+    #[test]
+    fn it_draws_the_week_with_rates_and_totals() {
+        let mut user_data = UserData::new();
+        user_data.add_habit("read".to_string(), habit_starting("10-1-2023", 1.0, None)).unwrap();
+        user_data.add_habit("walk".to_string(), habit_starting("10-1-2023", 5000.0, Some("1-3-5"))).unwrap();
+        let mut log = |habit: &str, command: &str, date: &str, value: f64| {
+            user_data.edit_habit_node(habit.to_string(), command, day(date), value, true).unwrap();
+        };
+        // The week of Monday 10-2-2023, looked at on Thursday 10-5
+        log("read", "complete", "10-2-2023", 0.0);
+        log("read", "skip", "10-3-2023", 0.0);
+        log("read", "fail", "10-4-2023", 0.0);
+        log("walk", "complete", "10-2-2023", 0.0);
+        log("walk", "set", "10-4-2023", 2500.0);
+        log("walk", "set", "10-7-2023", 800.0);
+
+        let grid = user_data.week_grid(day("10-4-2023"), day("10-5-2023"), &[], &Palette::new(false)).unwrap();
+        let expected = [
+            "Week of 10-2-2023 to 10-8-2023",
+            "       Mon     Tue     Wed     Thu     Fri     Sat     Sun     Rate",
+            "read   ✔       –       ✘       ○                               1/2 50%",
+            "walk   ✔ 5000  .       ◐ 2500  .               ◐ 800           1/2 50%",
+            "Total  2/2             0/2                                     2/4 50%",
+        ];
+        assert_eq!(grid, expected.join("\n"));
+
+        let last_day = HabitID::new(chrono::NaiveDate::MAX);
+        assert!(matches!(user_data.week_grid(last_day, day("10-5-2023"), &[], &Palette::new(false)), Err(HabitError::InvalidDate(_))));
+    }
+}